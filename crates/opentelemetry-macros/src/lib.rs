@@ -14,39 +14,50 @@
 
 //! This crate defines a macro for annotating `fn` and `impl` in order to wrap functions
 //! or methods with an `OpenTelemetry` context from Python using the `pyo3` crate.
-use std::collections::HashSet;
+use std::collections::{HashMap, HashSet};
 
 use proc_macro::TokenStream;
 use quote::{ToTokens, spanned::Spanned};
 use syn::{
     LitStr, Signature,
+    ext::IdentExt,
     meta::ParseNestedMeta,
     parse::{Parse, ParseStream},
     parse_macro_input,
 };
 
 const ERROR_UNSUPPORTED_ERROR_HANDLER: &str =
-    "error handlers must be one of py_error, trace, print, or ignore";
+    "error handlers must be one of py_error, trace, print, warn_once, or ignore";
 
-const ERROR_NO_ASYNC_SUPPORT: &str =
-    "pypropagate does not support async functions because there is no current support in pyo3";
+const ERROR_ONLY_FN_IMPL_OR_MOD: &str = "pypropagate can only be used on functions, impl blocks, or modules; \
+    to propagate to a pyclass, use it on the pymethods impl block or the enclosing module";
 
-const ERROR_ONLY_FN_OR_IMPL: &str = "pypropagate can only be used on functions or impl blocks";
+const ERROR_ONLY_INLINE_MOD: &str =
+    "pypropagate can only be used on modules whose contents are inline";
 
-const ERROR_SIGNATURE_MUST_INCLUDE_PY: &str =
-    "pypropagate requires a first function parameter of type `pyo3::Python<'_>`";
-
-const ERROR_UNEXPECTED_RECEIVER: &str = "found unexpected receiver argument";
+const ERROR_INCLUDE_ONLY_MOD: &str = "include can only be used on modules";
 
 const ERROR_UNKNOWN_CONFIGURATION_OPTION: &str = "unknown configuration option";
 
 const ERROR_INVALID_EXCLUDE: &str = "exclude should only contain impl method names";
 
+const ERROR_UNSUPPORTED_DIRECTION: &str =
+    "direction must be one of from_python, to_python, or both";
+
+const ERROR_NO_ASYNC_TO_PYTHON: &str =
+    "propagating the context to Python is not supported on async functions";
+
+const ERROR_UNSUPPORTED_SPAN_LEVEL: &str =
+    "span level must be one of trace, debug, info, warn, or error";
+
+const ERROR_UNKNOWN_SPAN_OPTION: &str = "unknown span option";
+
 #[derive(PartialEq, Debug)]
 enum RuntimeErrorHandler {
     PyError,
     Trace,
     Print,
+    WarnOnce,
     Ignore,
 }
 
@@ -57,6 +68,7 @@ impl Parse for RuntimeErrorHandler {
             "py_error" => Ok(Self::PyError),
             "trace" => Ok(Self::Trace),
             "print" => Ok(Self::Print),
+            "warn_once" => Ok(Self::WarnOnce),
             "ignore" => Ok(Self::Ignore),
             _ => Err(syn::Error::new(
                 value.span(),
@@ -69,11 +81,372 @@ impl Parse for RuntimeErrorHandler {
     }
 }
 
+/// Which way the `OpenTelemetry` context is propagated across the Python/Rust boundary.
+#[derive(PartialEq, Eq, Debug, Clone, Copy)]
+enum Direction {
+    /// Attach the calling Python context in Rust.
+    FromPython,
+    /// Attach the current Rust context in Python, for functions that call back into Python.
+    ToPython,
+    /// Attach the calling Python context in Rust, then the resulting Rust context in Python.
+    Both,
+}
+
+impl Direction {
+    const fn propagates_from_python(self) -> bool {
+        matches!(self, Self::FromPython | Self::Both)
+    }
+
+    const fn propagates_to_python(self) -> bool {
+        matches!(self, Self::ToPython | Self::Both)
+    }
+}
+
+impl Parse for Direction {
+    fn parse(input: ParseStream) -> syn::Result<Self> {
+        let value = input.parse::<LitStr>()?;
+        match value.value().as_str() {
+            "from_python" => Ok(Self::FromPython),
+            "to_python" => Ok(Self::ToPython),
+            "both" => Ok(Self::Both),
+            _ => Err(syn::Error::new(
+                value.span(),
+                format!(
+                    "{ERROR_UNSUPPORTED_DIRECTION}: {direction}",
+                    direction = value.value(),
+                ),
+            )),
+        }
+    }
+}
+
+/// Parse a parenthesized list of string patterns, e.g. `exclude("MyType.*", "helper")`.
+fn parse_patterns(meta: &ParseNestedMeta) -> syn::Result<Vec<String>> {
+    let content;
+    syn::parenthesized!(content in meta.input);
+    let patterns =
+        syn::punctuated::Punctuated::<LitStr, syn::Token![,]>::parse_terminated(&content)?;
+    Ok(patterns.iter().map(LitStr::value).collect())
+}
+
+/// Whether `name` matches `pattern`, in which `*` matches any sequence of characters.
+fn matches_pattern(pattern: &str, name: &str) -> bool {
+    match pattern.split_once('*') {
+        None => pattern == name,
+        Some((prefix, rest)) => {
+            let Some(name) = name.strip_prefix(prefix) else {
+                return false;
+            };
+            // Try every possible length for the sequence matched by this `*`.
+            name.char_indices()
+                .map(|(index, _)| index)
+                .chain([name.len()])
+                .any(|index| matches_pattern(rest, &name[index..]))
+        }
+    }
+}
+
+/// The level of the span created by `#[pypropagate(span)]`.
+#[derive(PartialEq, Eq, Debug, Clone, Copy, Default)]
+enum SpanLevel {
+    Trace,
+    Debug,
+    #[default]
+    Info,
+    Warn,
+    Error,
+}
+
+impl Parse for SpanLevel {
+    fn parse(input: ParseStream) -> syn::Result<Self> {
+        let value = input.parse::<LitStr>()?;
+        match value.value().as_str() {
+            "trace" => Ok(Self::Trace),
+            "debug" => Ok(Self::Debug),
+            "info" => Ok(Self::Info),
+            "warn" => Ok(Self::Warn),
+            "error" => Ok(Self::Error),
+            _ => Err(syn::Error::new(
+                value.span(),
+                format!(
+                    "{ERROR_UNSUPPORTED_SPAN_LEVEL}: {level}",
+                    level = value.value(),
+                ),
+            )),
+        }
+    }
+}
+
+impl ToTokens for SpanLevel {
+    fn to_tokens(&self, tokens: &mut proc_macro2::TokenStream) {
+        let level = match self {
+            Self::Trace => quote::quote!(TRACE),
+            Self::Debug => quote::quote!(DEBUG),
+            Self::Info => quote::quote!(INFO),
+            Self::Warn => quote::quote!(WARN),
+            Self::Error => quote::quote!(ERROR),
+        };
+        tokens.extend(quote::quote!(::pyo3_opentelemetry::__tracing::Level::#level));
+    }
+}
+
+/// A field recorded on the span created by `#[pypropagate(span)]`:
+/// either an argument name, which is recorded with its `Debug` implementation,
+/// or `name = value`, using `tracing`'s syntax for the value (e.g. `count = %count`).
+struct SpanField {
+    name: syn::Ident,
+    value: Option<proc_macro2::TokenStream>,
+}
+
+impl Parse for SpanField {
+    fn parse(input: ParseStream) -> syn::Result<Self> {
+        let name = input.parse()?;
+        let value = if input.parse::<Option<syn::Token![=]>>()?.is_some() {
+            let mut value = proc_macro2::TokenStream::new();
+            if let Some(sigil) = input.parse::<Option<syn::Token![?]>>()? {
+                sigil.to_tokens(&mut value);
+            } else if let Some(sigil) = input.parse::<Option<syn::Token![%]>>()? {
+                sigil.to_tokens(&mut value);
+            }
+            input.parse::<syn::Expr>()?.to_tokens(&mut value);
+            Some(value)
+        } else {
+            None
+        };
+        Ok(Self { name, value })
+    }
+}
+
+impl ToTokens for SpanField {
+    fn to_tokens(&self, tokens: &mut proc_macro2::TokenStream) {
+        let name = &self.name;
+        tokens.extend(self.value.as_ref().map_or_else(
+            || quote::quote!(#name = ?#name),
+            |value| quote::quote!(#name = #value),
+        ));
+    }
+}
+
+/// The configuration of the span created by `#[pypropagate(span)]`.
+#[derive(PartialEq, Debug, Default)]
+struct SpanConfiguration {
+    /// The span name; defaults to the Python qualified name.
+    name: Option<String>,
+    level: SpanLevel,
+    /// The Python module, overriding the enclosing `#[pymodule]` or, for methods, the type's.
+    module: Option<String>,
+    /// The fields to record, as tokens, which are parsed when the span is created.
+    fields: Vec<String>,
+}
+
+impl SpanConfiguration {
+    fn add_nested_meta(&mut self, meta: &ParseNestedMeta) -> syn::Result<()> {
+        if meta.path.is_ident("name") {
+            let value: LitStr = meta.value()?.parse()?;
+            self.name = Some(value.value());
+        } else if meta.path.is_ident("level") {
+            self.level = meta.value()?.parse()?;
+        } else if meta.path.is_ident("module") {
+            let value: LitStr = meta.value()?.parse()?;
+            self.module = Some(value.value());
+        } else if meta.path.is_ident("fields") {
+            let content;
+            syn::parenthesized!(content in meta.input);
+            let fields =
+                syn::punctuated::Punctuated::<SpanField, syn::Token![,]>::parse_terminated(
+                    &content,
+                )?;
+            self.fields.extend(
+                fields
+                    .iter()
+                    .map(|field| field.to_token_stream().to_string()),
+            );
+        } else {
+            return Err(syn::Error::new(
+                meta.path.__span(),
+                format!(
+                    "{ERROR_UNKNOWN_SPAN_OPTION}: {option}",
+                    option = meta.path.to_token_stream(),
+                ),
+            ));
+        }
+        Ok(())
+    }
+
+    /// An expression creating the span for the given function or method.
+    fn span(
+        &self,
+        function: &PythonFunction,
+    ) -> Result<proc_macro2::TokenStream, proc_macro2::LexError> {
+        let level = self.level;
+        let qualname = &function.qualname;
+        let name = self.name.as_ref().unwrap_or(qualname);
+        let mut fields = Vec::new();
+        let mut python_type = None;
+        if let Some(method) = &function.pymethod {
+            // The class's Python name and module are only looked up the first time it's called.
+            python_type = Some(quote::quote! {
+                static PYTHON_TYPE: ::pyo3_opentelemetry::__pyo3::sync::PyOnceLock<
+                    ::pyo3_opentelemetry::__PythonType,
+                > = ::pyo3_opentelemetry::__pyo3::sync::PyOnceLock::new();
+                let _pyo3_opentelemetry_python_type =
+                    ::pyo3_opentelemetry::__python_type_of::<Self>(&PYTHON_TYPE);
+            });
+            fields.push(quote::quote! {
+                python.qualname = %::std::format_args!(
+                    "{}.{}",
+                    _pyo3_opentelemetry_python_type.name,
+                    #method,
+                )
+            });
+        } else {
+            fields.push(quote::quote!(python.qualname = #qualname));
+        }
+        match self.module.as_ref().or(function.module.as_ref()) {
+            Some(module) => fields.push(quote::quote!(python.module = #module)),
+            None if python_type.is_some() => fields.push(quote::quote! {
+                python.module = _pyo3_opentelemetry_python_type.module.as_str()
+            }),
+            None => {}
+        }
+        for field in &self.fields {
+            fields.push(field.parse()?);
+        }
+        Ok(quote::quote! {
+            {
+                #python_type
+                ::pyo3_opentelemetry::__tracing::span!(#level, #name, #(#fields),*)
+            }
+        })
+    }
+}
+
+/// The Python identity of a function or method that `pypropagate` is applied to.
+struct PythonFunction {
+    /// The Python qualified name, e.g. `MyType.my_method`, as far as it's known from the macro's
+    /// input; a class defined elsewhere is named after its Rust type.
+    qualname: String,
+    /// The Python module of a function, if it's defined within a `#[pymodule]`.
+    module: Option<String>,
+    /// The Python name of a method in a `#[pymethods]` block, whose class name and module are
+    /// looked up from `Self` when it's called.
+    pymethod: Option<String>,
+}
+
+impl PythonFunction {
+    /// The Python name of a function, which may be overridden with `#[pyo3(name = "...")]`
+    /// or `#[pyfunction(name = "...")]`.
+    fn python_name(attrs: &[syn::Attribute], ident: &syn::Ident) -> String {
+        renamed_by(attrs, &["pyo3", "pyfunction"]).unwrap_or_else(|| ident.to_string())
+    }
+
+    /// A function, in the Python module `module` if it's known.
+    fn function(item_fn: &syn::ItemFn, module: Option<&str>) -> Self {
+        Self {
+            qualname: Self::python_name(&item_fn.attrs, &item_fn.sig.ident),
+            module: module.map(ToString::to_string),
+            pymethod: None,
+        }
+    }
+
+    /// A method of `self_ty`, whose class is named in `class_names` by its Rust name, falling
+    /// back to the Rust name. Only in a `#[pymethods]` block is `Self` known to be a Python class.
+    fn method(
+        self_ty: &syn::Type,
+        is_pymethods: bool,
+        item_method: &syn::ImplItemFn,
+        class_names: &HashMap<String, String>,
+    ) -> Self {
+        let name = Self::python_name(&item_method.attrs, &item_method.sig.ident);
+        let class_name = type_name(self_ty).map(|ty| class_names.get(&ty).cloned().unwrap_or(ty));
+        Self {
+            qualname: class_name.map_or_else(|| name.clone(), |ty| format!("{ty}.{name}")),
+            module: None,
+            pymethod: is_pymethods.then_some(name),
+        }
+    }
+}
+
+/// The `name = "..."` given by any of `attrs` that's one of `attr_names`, e.g. `pyclass`.
+fn renamed_by(attrs: &[syn::Attribute], attr_names: &[&str]) -> Option<String> {
+    pyo3_option(attrs, attr_names, "name")
+}
+
+/// The value of the string `option` given by any of `attrs` that's one of `attr_names`,
+/// e.g. `module` in `#[pymodule(module = "...")]`.
+fn pyo3_option(attrs: &[syn::Attribute], attr_names: &[&str], option: &str) -> Option<String> {
+    let mut value = None;
+    for attr in attrs {
+        let is_pyo3_attr = attr.path().segments.last().is_some_and(|segment| {
+            attr_names
+                .iter()
+                .any(|attr_name| segment.ident == attr_name)
+        });
+        if !is_pyo3_attr || !matches!(attr.meta, syn::Meta::List(_)) {
+            continue;
+        }
+        // Other options are none of our business, so errors parsing them are ignored.
+        let _ = attr.parse_nested_meta(|meta| {
+            if meta.path.is_ident(option) {
+                let option_value: LitStr = meta.value()?.parse()?;
+                value = Some(option_value.value());
+            } else if meta.input.peek(syn::Token![=]) {
+                meta.value()?.parse::<syn::Expr>()?;
+            } else if meta.input.peek(syn::token::Paren) {
+                meta.parse_nested_meta(|_| Ok(()))?;
+            }
+            Ok(())
+        });
+    }
+    value
+}
+
+/// The full Python name of `item_mod` if it's a `#[pymodule]`, within the `#[pymodule]` named
+/// `parent`, if any, following `PyO3`'s naming of declarative modules.
+fn pymodule_name(item_mod: &syn::ItemMod, parent: Option<&str>) -> Option<String> {
+    if !has_attribute(&item_mod.attrs, "pymodule") {
+        return None;
+    }
+    let attr_names = ["pymodule", "pyo3"];
+    let name = renamed_by(&item_mod.attrs, &attr_names)
+        .unwrap_or_else(|| item_mod.ident.unraw().to_string());
+    let parent = pyo3_option(&item_mod.attrs, &attr_names, "module")
+        .or_else(|| parent.map(ToString::to_string));
+    Some(parent.map_or_else(|| name.clone(), |parent| format!("{parent}.{name}")))
+}
+
+/// The Python names of the `#[pyclass(name = "...")]` structs and enums in a module,
+/// including nested modules, by their Rust names.
+fn pyclass_names(items: &[syn::Item], class_names: &mut HashMap<String, String>) {
+    for item in items {
+        let (attrs, ident) = match item {
+            syn::Item::Struct(item) => (&item.attrs, &item.ident),
+            syn::Item::Enum(item) => (&item.attrs, &item.ident),
+            syn::Item::Mod(item_mod) => {
+                if let Some((_, items)) = &item_mod.content {
+                    pyclass_names(items, class_names);
+                }
+                continue;
+            }
+            _ => continue,
+        };
+        if let Some(name) = renamed_by(attrs, &["pyclass"]) {
+            class_names.insert(ident.to_string(), name);
+        }
+    }
+}
+
 #[derive(PartialEq, Debug)]
 struct Configuration {
     on_context_extraction_failure: RuntimeErrorHandler,
     context_guard_name: String,
     exclude: HashSet<String>,
+    direction: Direction,
+    span: Option<SpanConfiguration>,
+    /// Patterns of Python qualified names to include, on modules; if empty, all are included.
+    include_patterns: Vec<String>,
+    /// Patterns of Python qualified names to exclude, on modules.
+    exclude_patterns: Vec<String>,
 }
 
 impl Default for Configuration {
@@ -82,6 +455,10 @@ impl Default for Configuration {
             on_context_extraction_failure: RuntimeErrorHandler::Print,
             context_guard_name: "_pyo3_opentelemetry_context_guard".to_string(),
             exclude: HashSet::new(),
+            direction: Direction::FromPython,
+            span: None,
+            include_patterns: Vec::new(),
+            exclude_patterns: Vec::new(),
         }
     }
 }
@@ -139,12 +516,49 @@ impl Configuration {
         }
     }
 
+    fn add_nested_meta_item_mod(&mut self, meta: &ParseNestedMeta) -> syn::Result<()> {
+        if meta.path.is_ident("include") {
+            self.include_patterns.extend(parse_patterns(meta)?);
+            Ok(())
+        } else if meta.path.is_ident("exclude") {
+            self.exclude_patterns.extend(parse_patterns(meta)?);
+            Ok(())
+        } else {
+            self.add_nested_meta(meta)
+        }
+    }
+
+    /// Whether a function or method in a module, with the given Python qualified name,
+    /// is selected by the `include` and `exclude` patterns.
+    fn includes(&self, qualname: &str) -> bool {
+        (self.include_patterns.is_empty()
+            || self
+                .include_patterns
+                .iter()
+                .any(|pattern| matches_pattern(pattern, qualname)))
+            && !self
+                .exclude_patterns
+                .iter()
+                .any(|pattern| matches_pattern(pattern, qualname))
+    }
+
     fn add_nested_meta(&mut self, meta: &ParseNestedMeta) -> syn::Result<()> {
+        if meta.path.is_ident("include") {
+            return Err(syn::Error::new(meta.path.__span(), ERROR_INCLUDE_ONLY_MOD));
+        }
         if meta.path.is_ident("on_context_extraction_failure") {
             self.on_context_extraction_failure = meta.value()?.parse()?;
         } else if meta.path.is_ident("context_guard_name") {
             let value: LitStr = meta.value()?.parse()?;
             self.context_guard_name = value.value();
+        } else if meta.path.is_ident("direction") {
+            self.direction = meta.value()?.parse()?;
+        } else if meta.path.is_ident("span") {
+            let mut span = SpanConfiguration::default();
+            if meta.input.peek(syn::token::Paren) {
+                meta.parse_nested_meta(|nested_meta| span.add_nested_meta(&nested_meta))?;
+            }
+            self.span = Some(span);
         } else {
             return Err(syn::Error::new(
                 meta.path.__span(),
@@ -158,110 +572,375 @@ impl Configuration {
     }
 }
 
+/// The statements that handle a failure to attach a context, stored in `guard_name`,
+/// according to the configured `on_context_extraction_failure`.
+fn attach_error_handler(
+    guard_name: &proc_macro2::TokenStream,
+    config: &Configuration,
+) -> proc_macro2::TokenStream {
+    match config.on_context_extraction_failure {
+        RuntimeErrorHandler::Trace => syn::parse_quote! {
+            if let Err(e) = #guard_name {
+                use ::pyo3_opentelemetry::__opentelemetry::trace::TraceContextExt;
+                let ctx = ::pyo3_opentelemetry::__opentelemetry::Context::current();
+                ctx.span().record_error(&e);
+            }
+        },
+        RuntimeErrorHandler::PyError => syn::parse_quote! {
+            let #guard_name = #guard_name?;
+        },
+        RuntimeErrorHandler::Print => syn::parse_quote! {
+            if let Err(e) = #guard_name {
+                eprintln!("{}", e);
+            }
+        },
+        RuntimeErrorHandler::WarnOnce => syn::parse_quote! {
+            if let Err(e) = #guard_name {
+                static WARNED: ::std::sync::Once = ::std::sync::Once::new();
+                WARNED.call_once(|| ::pyo3_opentelemetry::__warn_context_propagation_failure(&e));
+            }
+        },
+        RuntimeErrorHandler::Ignore => syn::parse_quote! {
+            let #guard_name = #guard_name.ok();
+        },
+    }
+}
+
+/// Call one of the `pyo3_opentelemetry` attach functions, which take a `Python` token.
+fn call_with_python(
+    function: &proc_macro2::TokenStream,
+    python_token: Option<&proc_macro2::TokenStream>,
+) -> proc_macro2::TokenStream {
+    python_token.map_or_else(
+        || quote::quote!(::pyo3_opentelemetry::__pyo3::Python::attach(#function)),
+        |py| quote::quote!(#function(#py)),
+    )
+}
+
 fn wrap_block_in_current_context(
     block: &mut syn::Block,
-    python_name: &proc_macro2::TokenStream,
+    python_token: Option<&proc_macro2::TokenStream>,
+    span: Option<&proc_macro2::TokenStream>,
     config: &Configuration,
 ) -> Result<(), proc_macro2::LexError> {
     let body = block.to_token_stream();
-    let context_guard_name: proc_macro2::TokenStream = config.context_guard_name.parse()?;
+    let mut attach_contexts = proc_macro2::TokenStream::new();
+    if config.direction.propagates_from_python() {
+        let context_guard_name: proc_macro2::TokenStream = config.context_guard_name.parse()?;
+        let attach_context = call_with_python(
+            &quote::quote!(pyo3_opentelemetry::attach_otel_context_from_python),
+            python_token,
+        );
+        let error_handler = attach_error_handler(&context_guard_name, config);
+        attach_contexts.extend(quote::quote! {
+            let #context_guard_name = #attach_context;
+            #error_handler
+        });
+    }
+    if let Some(span) = span {
+        // The span is created within the context from Python, so that it's a child of the
+        // Python span, and entered before the context is attached to Python, so that it's the
+        // parent of any spans created in Python.
+        attach_contexts.extend(quote::quote! {
+            let _pyo3_opentelemetry_span = #span;
+            let _pyo3_opentelemetry_span_guard = _pyo3_opentelemetry_span.enter();
+        });
+    }
+    if config.direction.propagates_to_python() {
+        // This is attached after the context from Python, so that it's detached first.
+        let python_guard_name = quote::quote!(_pyo3_opentelemetry_python_context_guard);
+        let attach_context = call_with_python(
+            &quote::quote!(pyo3_opentelemetry::attach_otel_context_to_python),
+            python_token,
+        );
+        let error_handler = attach_error_handler(&python_guard_name, config);
+        attach_contexts.extend(quote::quote! {
+            let #python_guard_name = #attach_context;
+            #error_handler
+        });
+    }
+    let current_context_setter = syn::parse_quote! {
+        {
+            #attach_contexts
+            #body
+        }
+    };
+    *block = current_context_setter;
+    Ok(())
+}
+
+/// Wrap the body of an `async fn` in a future with the current `OpenTelemetry` context from Python.
+///
+/// A context guard can't be held across `.await` points, so instead the context is extracted
+/// when the future is first polled, before any `.await`, and attached to the original body with
+/// `FutureExt::with_context`. `PyO3` doesn't allow `async fn`s to take a `Python` parameter,
+/// so the context is always extracted with `Python::attach`.
+fn wrap_async_block_in_current_context(
+    block: &mut syn::Block,
+    span: Option<&proc_macro2::TokenStream>,
+    config: &Configuration,
+) -> Result<(), proc_macro2::LexError> {
+    let mut body = block.to_token_stream();
+    if let Some(span) = span {
+        // The span must be created after the context is attached, to be a child of the Python span.
+        body = quote::quote! {
+            {
+                let _pyo3_opentelemetry_span = #span;
+                ::pyo3_opentelemetry::__tracing::Instrument::instrument(
+                    async move #body,
+                    _pyo3_opentelemetry_span,
+                )
+                .await
+            }
+        };
+    }
+    let context_name: proc_macro2::TokenStream = config.context_guard_name.parse()?;
     let error_handler: proc_macro2::TokenStream = match config.on_context_extraction_failure {
         RuntimeErrorHandler::Trace => syn::parse_quote! {
-            if let Err(e) = #context_guard_name {
+            #context_name.unwrap_or_else(|e| {
                 use ::pyo3_opentelemetry::__opentelemetry::trace::TraceContextExt;
                 let ctx = ::pyo3_opentelemetry::__opentelemetry::Context::current();
                 ctx.span().record_error(&e);
-            }
+                ctx
+            })
         },
         RuntimeErrorHandler::PyError => syn::parse_quote! {
-            let #context_guard_name = #context_guard_name?;
+            #context_name?
         },
         RuntimeErrorHandler::Print => syn::parse_quote! {
-            if let Err(e) = #context_guard_name {
+            #context_name.unwrap_or_else(|e| {
                 eprintln!("{}", e);
-            }
+                ::pyo3_opentelemetry::__opentelemetry::Context::current()
+            })
+        },
+        RuntimeErrorHandler::WarnOnce => syn::parse_quote! {
+            #context_name.unwrap_or_else(|e| {
+                static WARNED: ::std::sync::Once = ::std::sync::Once::new();
+                WARNED.call_once(|| ::pyo3_opentelemetry::__warn_context_propagation_failure(&e));
+                ::pyo3_opentelemetry::__opentelemetry::Context::current()
+            })
         },
         RuntimeErrorHandler::Ignore => syn::parse_quote! {
-            let #context_guard_name = #context_guard_name.ok();
+            #context_name.unwrap_or_else(|_| ::pyo3_opentelemetry::__opentelemetry::Context::current())
         },
     };
     let current_context_setter = syn::parse_quote! {
         {
-            let #context_guard_name  = pyo3_opentelemetry::attach_otel_context_from_python(#python_name);
-            #error_handler
-            #body
+            let #context_name = ::pyo3_opentelemetry::__pyo3::Python::attach(
+                ::pyo3_opentelemetry::otel_context_from_python,
+            );
+            let #context_name = #error_handler;
+            ::pyo3_opentelemetry::__opentelemetry::trace::FutureExt::with_context(
+                async move #body,
+                #context_name,
+            )
+            .await
         }
     };
     *block = current_context_setter;
     Ok(())
 }
 
-fn validate_signature(signature: &Signature) -> syn::Result<()> {
-    if signature.asyncness.is_some() {
-        return Err(syn::Error::new(
-            signature.asyncness.__span(),
-            ERROR_NO_ASYNC_SUPPORT,
-        ));
-    }
-    Ok(())
+/// Find an expression for the `Python` token among the function parameters.
+///
+/// This is a `Python` parameter if there is one, wherever it is in the signature.
+/// Otherwise, it's derived from the first parameter with a `py()` method,
+/// such as `slf: PyRef<'_, Self>` or `obj: &Bound<'_, PyAny>`.
+/// Returns `None` if there's neither, in which case the caller should use `Python::attach`.
+fn get_python_token(signature: &Signature) -> Option<proc_macro2::TokenStream> {
+    let typed_params = || {
+        signature.inputs.iter().filter_map(|arg| match arg {
+            syn::FnArg::Typed(arg) => match &*arg.pat {
+                syn::Pat::Ident(pat) => Some((&pat.ident, type_name(&arg.ty)?)),
+                _ => None,
+            },
+            syn::FnArg::Receiver(_) => None,
+        })
+    };
+
+    typed_params()
+        .find(|(_, ty)| ty == "Python")
+        .map(|(name, _)| name.to_token_stream())
+        .or_else(|| {
+            typed_params()
+                .find(|(_, ty)| PYTHON_BOUND_TYPES.contains(&ty.as_str()))
+                .map(|(name, _)| quote::quote!(#name.py()))
+        })
 }
 
-fn get_python_parameter_name(signature: &Signature) -> syn::Result<proc_macro2::TokenStream> {
-    let mut first_arg = signature
-        .inputs
-        .first()
-        .ok_or_else(|| syn::Error::new(signature.__span(), ERROR_SIGNATURE_MUST_INCLUDE_PY))?;
-    if let syn::FnArg::Receiver(_) = first_arg {
-        first_arg =
-            signature.inputs.iter().nth(1).ok_or_else(|| {
-                syn::Error::new(signature.__span(), ERROR_SIGNATURE_MUST_INCLUDE_PY)
-            })?;
-    }
+/// Types, other than `Python` itself, that are bound to the `Python` token and have a `py()` method.
+const PYTHON_BOUND_TYPES: &[&str] = &["Bound", "Borrowed", "PyRef", "PyRefMut"];
 
-    match first_arg {
-        syn::FnArg::Typed(arg) => Ok(arg.pat.to_token_stream()),
-        syn::FnArg::Receiver(_) => Err(syn::Error::new(
-            first_arg.__span(),
-            ERROR_UNEXPECTED_RECEIVER,
-        )),
+/// The name of a (possibly referenced) type, without its path or generic arguments.
+fn type_name(ty: &syn::Type) -> Option<String> {
+    match ty {
+        syn::Type::Path(ty) => ty
+            .path
+            .segments
+            .last()
+            .map(|segment| segment.ident.to_string()),
+        syn::Type::Reference(ty) => type_name(&ty.elem),
+        syn::Type::Paren(ty) => type_name(&ty.elem),
+        syn::Type::Group(ty) => type_name(&ty.elem),
+        _ => None,
     }
 }
 
 fn pypropagate_signature_and_method(
     signature: &Signature,
     block: &mut syn::Block,
+    function: &PythonFunction,
     config: &Configuration,
 ) -> Result<(), syn::Error> {
-    validate_signature(signature)?;
-    let python_name = get_python_parameter_name(signature)?;
+    let span = config
+        .span
+        .as_ref()
+        .map(|span| span.span(function))
+        .transpose()?;
 
-    wrap_block_in_current_context(block, &python_name, config)?;
+    if signature.asyncness.is_some() {
+        if config.direction.propagates_to_python() {
+            return Err(syn::Error::new(
+                signature.asyncness.__span(),
+                ERROR_NO_ASYNC_TO_PYTHON,
+            ));
+        }
+        wrap_async_block_in_current_context(block, span.as_ref(), config)?;
+        return Ok(());
+    }
+
+    let python_token = get_python_token(signature);
+
+    wrap_block_in_current_context(block, python_token.as_ref(), span.as_ref(), config)?;
+    Ok(())
+}
+
+/// Whether any of `attrs` is the attribute `name`, e.g. `pyfunction` or `pymethods`,
+/// either directly or within `cfg_attr`.
+fn has_attribute(attrs: &[syn::Attribute], name: &str) -> bool {
+    attrs.iter().any(|attr| {
+        if attr
+            .path()
+            .segments
+            .last()
+            .is_some_and(|segment| segment.ident == name)
+        {
+            return true;
+        }
+        attr.path().is_ident("cfg_attr")
+            && attr.meta.require_list().is_ok_and(|list| {
+                list.tokens.clone().into_iter().any(
+                    |token| matches!(token, proc_macro2::TokenTree::Ident(ident) if ident == name),
+                )
+            })
+    })
+}
+
+/// Apply `pypropagate` to every `#[pyfunction]` and every method in a `#[pymethods]` block
+/// within a module, including nested modules, that's selected by the `include` and `exclude`
+/// patterns. Items with their own `#[pypropagate]` attribute are left for it to configure.
+/// Methods are named after the Python names of their classes, given by `class_names`,
+/// and functions are in `module`, the full name of the enclosing `#[pymodule]`, if any.
+fn pypropagate_mod(
+    items: &mut [syn::Item],
+    config: &Configuration,
+    class_names: &HashMap<String, String>,
+    module: Option<&str>,
+) -> Result<(), syn::Error> {
+    for item in items {
+        match item {
+            syn::Item::Fn(item_fn)
+                if has_attribute(&item_fn.attrs, "pyfunction")
+                    && !has_attribute(&item_fn.attrs, "pypropagate") =>
+            {
+                let function = PythonFunction::function(item_fn, module);
+                if config.includes(&function.qualname) {
+                    pypropagate_signature_and_method(
+                        &item_fn.sig,
+                        &mut item_fn.block,
+                        &function,
+                        config,
+                    )?;
+                }
+            }
+            syn::Item::Impl(item_impl)
+                if has_attribute(&item_impl.attrs, "pymethods")
+                    && !has_attribute(&item_impl.attrs, "pypropagate") =>
+            {
+                for impl_item in &mut item_impl.items {
+                    if let syn::ImplItem::Fn(item_method) = impl_item {
+                        let function = PythonFunction::method(
+                            &item_impl.self_ty,
+                            true,
+                            item_method,
+                            class_names,
+                        );
+                        if config.includes(&function.qualname) {
+                            pypropagate_signature_and_method(
+                                &item_method.sig,
+                                &mut item_method.block,
+                                &function,
+                                config,
+                            )?;
+                        }
+                    }
+                }
+            }
+            syn::Item::Mod(item_mod) if !has_attribute(&item_mod.attrs, "pypropagate") => {
+                let module = pymodule_name(item_mod, module);
+                if let Some((_, items)) = &mut item_mod.content {
+                    pypropagate_mod(items, config, class_names, module.as_deref())?;
+                }
+            }
+            _ => {}
+        }
+    }
     Ok(())
 }
 
 fn pypropagate_impl(item: syn::Item, config: &Configuration) -> Result<syn::Item, syn::Error> {
     match item {
         syn::Item::Fn(mut item_fn) => {
-            pypropagate_signature_and_method(&item_fn.sig, &mut item_fn.block, config)?;
+            let function = PythonFunction::function(&item_fn, None);
+            pypropagate_signature_and_method(&item_fn.sig, &mut item_fn.block, &function, config)?;
 
             Ok(syn::Item::Fn(item_fn))
         }
         syn::Item::Impl(mut item_impl) => {
+            let is_pymethods = has_attribute(&item_impl.attrs, "pymethods");
             for mut item in &mut item_impl.items {
                 if let syn::ImplItem::Fn(item_method) = &mut item {
                     if config.exclude.contains(&item_method.sig.ident.to_string()) {
                         continue;
                     }
+                    // The `#[pyclass]` isn't visible here, so the Rust type name is used.
+                    let function = PythonFunction::method(
+                        &item_impl.self_ty,
+                        is_pymethods,
+                        item_method,
+                        &HashMap::new(),
+                    );
                     pypropagate_signature_and_method(
                         &item_method.sig,
                         &mut item_method.block,
+                        &function,
                         config,
                     )?;
                 }
             }
             Ok(syn::Item::Impl(item_impl))
         }
-        _ => Err(syn::Error::new_spanned(item, ERROR_ONLY_FN_OR_IMPL)),
+        syn::Item::Mod(mut item_mod) => {
+            let module = pymodule_name(&item_mod, None);
+            let Some((_, items)) = &mut item_mod.content else {
+                return Err(syn::Error::new_spanned(item_mod, ERROR_ONLY_INLINE_MOD));
+            };
+            let mut class_names = HashMap::new();
+            pyclass_names(items, &mut class_names);
+            pypropagate_mod(items, config, &class_names, module.as_deref())?;
+            Ok(syn::Item::Mod(item_mod))
+        }
+        _ => Err(syn::Error::new_spanned(item, ERROR_ONLY_FN_IMPL_OR_MOD)),
     }
 }
 
@@ -277,16 +956,29 @@ fn get_item_impl_method_names(item_impl: &syn::ItemImpl) -> HashSet<String> {
 
 /// Ensure the wrapped function or method executes from Python in the current OpenTelemetry context.
 ///
+/// The macro can also be applied to an inline module, in which case it applies to every
+/// `#[pyfunction]` and every method of every `#[pymethods]` block within it, including within nested
+/// modules, that's selected by the `include` and `exclude` options. Items within the module that have
+/// their own `#[pypropagate]` attribute are configured by it instead. Other items, such as
+/// `#[pyclass]` structs, are left as they are; the macro can't be applied to them directly.
+///
 /// This macro prepends `pyo3_opentelemetry::attach_otel_context_from_python` to the function or method body,
 /// effectively ensuring that the wrapped function or method is executed in the current `OpenTelemetry` context
-/// from the Python side.
+/// from the Python side. For `async` functions, the body is instead wrapped in a future with that context,
+/// using `FutureExt::with_context`.
 ///
 /// # Requirements and Limitations
 ///
-/// * The first non-receiver parameter MUST be `pyo3::Python`.
+/// * The `pyo3::Python` token is taken from a `Python` parameter anywhere in the signature or,
+///   failing that, from a parameter with a `py()` method, such as `slf: PyRef<'_, Self>`
+///   or `&Bound<'_, PyAny>`. Without either, the macro uses `Python::attach`.
 /// * The macro MUST be invoked on the outside of `pyfunction` or `pymethods`.
-/// * The calling Python code must have [opentelemetry-api](https://pypi.org/project/opentelemetry-api/) installed.
-/// * `async` function signatures are not supported as they are not supported in `pyo3`.
+/// * The calling Python code should have [opentelemetry-api](https://pypi.org/project/opentelemetry-api/) installed.
+///   If it doesn't, the macro does nothing, other than emitting a single Python `RuntimeWarning`
+///   the first time the context would have been propagated; this isn't considered a failure.
+/// * `async` functions don't need a `pyo3::Python` parameter, and `PyO3` doesn't allow them to have one.
+///   Instead, the context is extracted when the returned future is first polled, which `PyO3` does
+///   when the coroutine is first awaited from Python, and attached to the future for as long as it runs.
 ///
 /// # Configuration
 ///
@@ -295,10 +987,41 @@ fn get_item_impl_method_names(item_impl: &syn::ItemImpl) -> HashSet<String> {
 ///   - must be a valid Rust identifier.
 /// - `on_context_extraction_failure`: What to do when the context cannot be extracted from Python. Defaults to `print`.
 ///   - `print`: Print the error to stderr and continue.
+///   - `warn_once`: Emit a Python `RuntimeWarning` the first time the function fails to extract
+///     the context, and continue.
 ///   - `trace`: Record the error on the current span using `opentelemetry::trace::TraceContextExt::record_error`.
 ///   - `py_error`: Return a `pyo3::PyErr`.
 ///   - `ignore`: Ignore the error.
-/// - `exclude`: A list of method names to exclude. Only valid on `impl` items.
+/// - `exclude`: On `impl` items, a list of method names to exclude. On modules, a list of patterns
+///   of Python qualified names to exclude, in which `*` matches anything, e.g. `exclude("MyType.*")`.
+///   Classes are named as in Python, following `#[pyclass(name = "...")]` when the class is
+///   defined within the module.
+/// - `include`: A list of patterns of Python qualified names to include. Only valid on modules.
+///   Defaults to including everything that isn't excluded.
+/// - `direction`: Which way to propagate the context. Defaults to `from_python`.
+///   - `from_python`: Attach the calling Python context in Rust.
+///   - `to_python`: Attach the current Rust context in Python, using
+///     `pyo3_opentelemetry::attach_otel_context_to_python`, so that spans started by Python
+///     code that the function calls are children of the current Rust span.
+///     `on_context_extraction_failure` also applies to failures to attach the context in Python.
+///     Not supported on `async` functions.
+///   - `both`: Attach the calling Python context in Rust, then the resulting Rust context in Python.
+///     Use it with `span` for spans started in Python to be children of a Rust span;
+///     `#[instrument]` enters its span before the context from Python is attached.
+/// - `span`: Create and enter a `tracing` span within the context from Python, so that it's always
+///   a child of the Python span, regardless of how the macro is ordered relative to `#[pyfunction]`,
+///   unlike `#[instrument]`. The span records the Python qualified name as `python.qualname` and
+///   the Python module as `python.module`. For methods in a `#[pymethods]` block, both are looked
+///   up from the class the first time they're called; for functions, the module is that of the
+///   enclosing `#[pymodule]` when the macro is applied to it. Optionally configured with:
+///   - `name`: The span name. Defaults to the Python qualified name, e.g. `MyType.my_method`,
+///     which must be known when the macro is expanded: on an `impl` block, which can't see the
+///     `#[pyclass]`, the class is named after the Rust type.
+///   - `level`: One of `trace`, `debug`, `info`, `warn`, or `error`. Defaults to `info`.
+///   - `module`: The Python module to record, e.g. for a function that the macro is applied to
+///     directly, whose module isn't known until it's added to one.
+///   - `fields`: Arguments to record with their `Debug` implementations, or `name = value` fields
+///     using `tracing`'s syntax, e.g. `fields(arg1, length = arg2.len(), text = %arg2)`.
 ///
 /// # Examples
 ///
@@ -313,6 +1036,12 @@ fn get_item_impl_method_names(item_impl: &syn::ItemImpl) -> HashSet<String> {
 ///    Ok(())
 /// }
 ///
+/// #[pypropagate(span(level = "debug", fields(arg1)))]
+/// #[pyfunction]
+/// fn my_spanned_function(arg1: u32) -> u32 {
+///    arg1 + 1
+/// }
+///
 /// #[pypropagate(context_guard_name = "_my_context_guard", on_context_extraction_failure = "py_error")]
 /// #[pyfunction]
 /// fn my_function2(py: Python<'_>, arg1: u32, arg2: String) -> PyResult<()> {
@@ -334,6 +1063,11 @@ fn get_item_impl_method_names(item_impl: &syn::ItemImpl) -> HashSet<String> {
 ///      // ...
 ///      Ok(())
 ///    }
+///
+///    async fn my_async_method(&self, arg1: u32) -> PyResult<()> {
+///      // ...
+///      Ok(())
+///    }
 /// }
 /// ```
 #[proc_macro_attribute]
@@ -350,6 +1084,9 @@ pub fn pypropagate(attr: TokenStream, item: TokenStream) -> TokenStream {
                 config.add_nested_meta_item_impl(&meta, &valid_exclusions)
             });
             parse_macro_input!(attr with config_parser);
+        } else if let syn::Item::Mod(_) = &item {
+            let config_parser = syn::meta::parser(|meta| config.add_nested_meta_item_mod(&meta));
+            parse_macro_input!(attr with config_parser);
         } else {
             let config_parser = syn::meta::parser(|meta| config.add_nested_meta_item_fn(&meta));
             parse_macro_input!(attr with config_parser);
@@ -375,6 +1112,7 @@ mod test {
     #[case("#[pypropagate(on_context_extraction_failure = \"trace\")]", Configuration { on_context_extraction_failure: RuntimeErrorHandler::Trace, ..Default::default() })]
     #[case("#[pypropagate(on_context_extraction_failure = \"print\")]", Configuration { on_context_extraction_failure: RuntimeErrorHandler::Print, ..Default::default() })]
     #[case("#[pypropagate(on_context_extraction_failure = \"ignore\")]", Configuration { on_context_extraction_failure: RuntimeErrorHandler::Ignore, ..Default::default() })]
+    #[case("#[pypropagate(on_context_extraction_failure = \"warn_once\")]", Configuration { on_context_extraction_failure: RuntimeErrorHandler::WarnOnce, ..Default::default() })]
     #[case("#[pypropagate(on_context_extraction_failure = \"print\", context_guard_name = \"_my_guard\")]", Configuration { on_context_extraction_failure: RuntimeErrorHandler::Print, context_guard_name: "_my_guard".to_string(), ..Default::default() })]
     #[case(
         "#[pypropagate(context_guard_name = \"_my_guard\")]",
@@ -384,6 +1122,21 @@ mod test {
         "#[pypropagate(exclude(my_method))]",
         Configuration { exclude: HashSet::from(["my_method".to_string()]), ..Configuration::default() }
     )]
+    #[case("#[pypropagate(direction = \"to_python\")]", Configuration { direction: Direction::ToPython, ..Default::default() })]
+    #[case("#[pypropagate(direction = \"both\")]", Configuration { direction: Direction::Both, ..Default::default() })]
+    #[case("#[pypropagate(span)]", Configuration { span: Some(SpanConfiguration::default()), ..Default::default() })]
+    #[case(
+        "#[pypropagate(span(name = \"shave\", level = \"debug\", module = \"yaks\", fields(yak, count = %count)))]",
+        Configuration {
+            span: Some(SpanConfiguration {
+                name: Some("shave".to_string()),
+                level: SpanLevel::Debug,
+                module: Some("yaks".to_string()),
+                fields: vec!["yak = ? yak".to_string(), "count = % count".to_string()],
+            }),
+            ..Default::default()
+        }
+    )]
     fn test_configuration_parsing(#[case] attr: &str, #[case] expected: Configuration) {
         let mut config = Configuration::default();
         let tokens: proc_macro2::TokenStream = syn::parse_str(attr).unwrap();
@@ -410,6 +1163,10 @@ mod test {
     #[case("#[pypropagate(not_an_option = \"\")]", [ERROR_UNKNOWN_CONFIGURATION_OPTION, ": not_an_option"])]
     #[case("#[pypropagate(on_context_extraction_failure = \"not_py_error\")]", [ERROR_UNSUPPORTED_ERROR_HANDLER, ": not_py_error"])]
     #[case("#[pypropagate(exclude(not::an::ident))]", [ERROR_INVALID_EXCLUDE, ": not::an::ident"])]
+    #[case("#[pypropagate(direction = \"sideways\")]", [ERROR_UNSUPPORTED_DIRECTION, ": sideways"])]
+    #[case("#[pypropagate(span(level = \"loud\"))]", [ERROR_UNSUPPORTED_SPAN_LEVEL, ": loud"])]
+    #[case("#[pypropagate(span(colour = \"red\"))]", [ERROR_UNKNOWN_SPAN_OPTION, ": colour"])]
+    #[case("#[pypropagate(include(\"Yak.*\"))]", [ERROR_INCLUDE_ONLY_MOD, ""])]
     fn test_misconfiguration_errors(#[case] attr: &str, #[case] expected_error: [&str; 2]) {
         let mut config = Configuration::default();
         let tokens: proc_macro2::TokenStream = syn::parse_str(attr).unwrap();
@@ -424,40 +1181,28 @@ mod test {
         assert_eq!(error.to_string(), expected_error.join(""));
     }
 
-    const MISSING_PY_FN_PARAMETER: &str = r"
-        fn my_function() {}
-    ";
-
-    const MISSING_PY_IMPL_PARAMETER: &str = r"
-        impl MyType {
-            fn my_method(&self) {}
-        }
-    ";
-
-    const ASYNC_FUNCTION: &str = r"
-        async fn my_function() {}
-    ";
-
-    const ASYNC_METHOD: &str = r"
-        impl MyType {
-            async fn my_method(&self) {}
-        }";
-
     const STRUCT: &str = r"
         struct MyStruct;
         ";
 
     /// Test that invalid macro invocations result in the correct error messages.
     #[rstest]
-    #[case(MISSING_PY_FN_PARAMETER, ERROR_SIGNATURE_MUST_INCLUDE_PY)]
-    #[case(MISSING_PY_IMPL_PARAMETER, ERROR_SIGNATURE_MUST_INCLUDE_PY)]
-    #[case(ASYNC_FUNCTION, ERROR_NO_ASYNC_SUPPORT)]
-    #[case(ASYNC_METHOD, ERROR_NO_ASYNC_SUPPORT)]
-    #[case(STRUCT, ERROR_ONLY_FN_OR_IMPL)]
-    fn test_pypropagate_macro_errors(#[case] code: &str, #[case] error: &str) {
+    #[case(STRUCT, Configuration::default(), ERROR_ONLY_FN_IMPL_OR_MOD)]
+    #[case(
+        "#[pyclass] struct Yak;",
+        Configuration::default(),
+        ERROR_ONLY_FN_IMPL_OR_MOD
+    )]
+    #[case("mod yaks;", Configuration::default(), ERROR_ONLY_INLINE_MOD)]
+    #[case(ASYNC_FUNCTION, Configuration { direction: Direction::ToPython, ..Default::default() }, ERROR_NO_ASYNC_TO_PYTHON)]
+    #[case(ASYNC_METHODS, Configuration { direction: Direction::Both, ..Default::default() }, ERROR_NO_ASYNC_TO_PYTHON)]
+    fn test_pypropagate_macro_errors(
+        #[case] code: &str,
+        #[case] config: Configuration,
+        #[case] error: &str,
+    ) {
         let tokens: proc_macro2::TokenStream = syn::parse_str(code).unwrap();
         let item = syn::parse2::<syn::Item>(tokens).unwrap();
-        let config = Configuration::default();
         let result = pypropagate_impl(item, &config)
             .map(|item| item.to_token_stream())
             .unwrap_err();
@@ -515,10 +1260,41 @@ mod test {
             fn my_method2(&self, py: Python<'_>) {}
         }";
 
+    const ASYNC_FUNCTION: &str = r"
+        async fn my_function() {}
+    ";
+
+    const ASYNC_METHODS: &str = r"
+        impl MyType {
+            async fn my_method1(&self) {}
+
+            fn my_method2(&self, py: Python<'_>) {}
+        }";
+
+    const MISSING_PY_FN_PARAMETER: &str = r"
+        fn my_function() {}
+    ";
+
+    const MISSING_PY_IMPL_PARAMETER: &str = r"
+        impl MyType {
+            fn my_method(&self) {}
+        }
+    ";
+
     /// Test that valid macro invocations do not result in errors.
     #[rstest]
+    #[case(MISSING_PY_FN_PARAMETER, Configuration::default())]
+    #[case(MISSING_PY_IMPL_PARAMETER, Configuration::default())]
     #[case(VALID_FUNCTION, Configuration::default())]
+    #[case(ASYNC_FUNCTION, Configuration::default())]
+    #[case(ASYNC_FUNCTION, Configuration { on_context_extraction_failure: RuntimeErrorHandler::WarnOnce, ..Default::default() })]
+    #[case(VALID_FUNCTION, Configuration { on_context_extraction_failure: RuntimeErrorHandler::WarnOnce, direction: Direction::Both, ..Default::default() })]
+    #[case(ASYNC_METHODS, Configuration { on_context_extraction_failure: RuntimeErrorHandler::PyError, ..Default::default() })]
     #[case(VALID_METHODS, Configuration{ exclude: HashSet::from(["my_method1".to_string()]), ..Default::default() })]
+    #[case(VALID_FUNCTION, Configuration { direction: Direction::ToPython, ..Default::default() })]
+    #[case(MISSING_PY_IMPL_PARAMETER, Configuration { direction: Direction::Both, ..Default::default() })]
+    #[case(ASYNC_METHODS, Configuration { span: Some(SpanConfiguration::default()), ..Default::default() })]
+    #[case(VALID_FUNCTION, Configuration { span: Some(SpanConfiguration::default()), direction: Direction::Both, ..Default::default() })]
     fn test_valid(#[case] code: &str, #[case] config: Configuration) {
         let tokens: proc_macro2::TokenStream = syn::parse_str(code).unwrap();
         let item = syn::parse2::<syn::Item>(tokens).unwrap();
@@ -526,4 +1302,211 @@ mod test {
             .map(|item| item.to_token_stream())
             .expect("Should not fail");
     }
+
+    /// Test that the `Python` token is found wherever it is in the signature, or derived from
+    /// another parameter.
+    #[rstest]
+    #[case("fn f(py: Python<'_>)", Some("py"))]
+    #[case("fn f(&self, x: u32, _py: pyo3::Python<'_>)", Some("_py"))]
+    #[case("fn f(obj: &Bound<'_, PyAny>, py: Python<'_>)", Some("py"))]
+    #[case("fn f(slf: PyRef<'_, Self>, x: u32)", Some("slf . py ()"))]
+    #[case("fn f(x: u32, obj: &pyo3::Bound<'_, PyAny>)", Some("obj . py ()"))]
+    #[case("fn f(&self, x: u32)", None)]
+    #[case("fn f(_: Python<'_>)", None)]
+    fn test_get_python_token(#[case] signature: &str, #[case] expected: Option<&str>) {
+        let signature: Signature = syn::parse_str(signature).unwrap();
+        assert_eq!(
+            get_python_token(&signature).map(|token| token.to_string()),
+            expected.map(str::to_string)
+        );
+    }
+
+    /// Test that the Python qualified name of functions and methods respects renaming.
+    #[rstest]
+    #[case("#[pyfunction] fn shave() {}", "shave")]
+    #[case("#[pyfunction(name = \"shave_yak\")] fn shave() {}", "shave_yak")]
+    #[case(
+        "#[pyfunction(signature = (x = 1), name = \"shave_yak\")] fn shave(x: u32) {}",
+        "shave_yak"
+    )]
+    #[case(
+        "impl Yak { #[pyo3(name = \"shave_yak\")] fn shave(&self) {} }",
+        "Yak.shave_yak"
+    )]
+    #[case("impl crate::Yak { #[getter] fn hair(&self) {} }", "Yak.hair")]
+    fn test_python_function_qualname(#[case] code: &str, #[case] expected: &str) {
+        let function = match syn::parse_str::<syn::Item>(code).unwrap() {
+            syn::Item::Fn(item_fn) => PythonFunction::function(&item_fn, None),
+            syn::Item::Impl(item_impl) => match &item_impl.items[0] {
+                syn::ImplItem::Fn(item_method) => {
+                    PythonFunction::method(&item_impl.self_ty, true, item_method, &HashMap::new())
+                }
+                _ => panic!("Invalid impl item"),
+            },
+            _ => panic!("Invalid item type"),
+        };
+        assert_eq!(function.qualname, expected);
+    }
+
+    /// Test how the span records the Python qualified name and module of functions and methods.
+    #[rstest]
+    #[case("#[pyfunction] fn shave() {}", &["python . qualname = \"shave\""], &["python . module"])]
+    #[case(
+        "#[pymethods] impl Yak { fn groom(&self) {} }",
+        &[
+            "__python_type_of :: < Self >",
+            "_pyo3_opentelemetry_python_type . name , \"groom\"",
+            "python . module = _pyo3_opentelemetry_python_type . module",
+        ],
+        &[]
+    )]
+    #[case(
+        "impl Yak { fn groom(&self) {} }",
+        &["python . qualname = \"Yak.groom\""],
+        &["__python_type_of", "python . module"]
+    )]
+    #[case(
+        "#[pymodule] mod yaks { #[pymodule(name = \"herd\")] mod nested { #[pyfunction] fn shave() {} } }",
+        &["python . module = \"yaks.herd\""],
+        &[]
+    )]
+    #[case(
+        "#[pymodule(module = \"farm\")] mod yaks { #[pyfunction] fn shave() {} }",
+        &["python . module = \"farm.yaks\""],
+        &[]
+    )]
+    #[case("mod yaks { #[pyfunction] fn shave() {} }", &[], &["python . module"])]
+    fn test_span_python_identity(
+        #[case] code: &str,
+        #[case] expected: &[&str],
+        #[case] unexpected: &[&str],
+    ) {
+        let config = Configuration {
+            span: Some(SpanConfiguration::default()),
+            ..Default::default()
+        };
+        let item = syn::parse_str::<syn::Item>(code).unwrap();
+        let tokens = pypropagate_impl(item, &config)
+            .unwrap()
+            .to_token_stream()
+            .to_string();
+        for expected in expected {
+            assert!(tokens.contains(expected), "{expected} not in {tokens}");
+        }
+        for unexpected in unexpected {
+            assert!(!tokens.contains(unexpected), "{unexpected} in {tokens}");
+        }
+    }
+
+    #[rstest]
+    #[case("shave", "shave", true)]
+    #[case("shave", "shaved", false)]
+    #[case("*", "Yak.shave", true)]
+    #[case("Yak.*", "Yak.shave", true)]
+    #[case("Yak.*", "Yakety.shave", false)]
+    #[case("*.shave", "Yak.shave", true)]
+    #[case("*.shave", "shave", false)]
+    #[case("sh*ve*", "shave_yak", true)]
+    #[case("s*e", "shave_yak", false)]
+    fn test_matches_pattern(#[case] pattern: &str, #[case] name: &str, #[case] expected: bool) {
+        assert_eq!(matches_pattern(pattern, name), expected);
+    }
+
+    const MODULE: &str = r#"
+        mod yaks {
+            #[pyfunction]
+            fn shave() {}
+
+            fn helper() {}
+
+            #[pypropagate(span)]
+            #[pyfunction]
+            fn configured_separately() {}
+
+            #[pymethods]
+            impl Yak {
+                fn groom(&self) {}
+
+                fn __repr__(&self) -> String {
+                    String::new()
+                }
+            }
+
+            impl Yak {
+                fn internal(&self) {}
+            }
+
+            mod nested {
+                #[cfg_attr(feature = "python", pyfunction)]
+                fn nested_shave() {}
+
+                #[pyclass(name = "Bison", frozen)]
+                struct RustBison;
+            }
+
+            #[pymethods]
+            impl nested::RustBison {
+                fn graze(&self) {}
+            }
+        }
+    "#;
+
+    /// Test that `pypropagate` on a module applies to the selected `pyfunction`s and `pymethods`.
+    #[rstest]
+    #[case("#[pypropagate]", &["shave", "groom", "__repr__", "nested_shave", "graze"])]
+    #[case("#[pypropagate(include(\"Yak.*\"))]", &["groom", "__repr__"])]
+    #[case("#[pypropagate(include(\"Bison.*\"))]", &["graze"])]
+    #[case("#[pypropagate(include(\"RustBison.*\"))]", &[])]
+    #[case("#[pypropagate(exclude(\"*.__*__\", \"nested_*\", \"Bison.*\"))]", &["shave", "groom"])]
+    #[case("#[pypropagate(include(\"*shave\"), exclude(\"nested_*\"))]", &["shave"])]
+    fn test_pypropagate_mod(#[case] attr: &str, #[case] expected: &[&str]) {
+        let mut config = Configuration::default();
+        let tokens: proc_macro2::TokenStream = syn::parse_str(attr).unwrap();
+        let attr: syn::Attribute = parse_quote! {
+            #tokens
+        };
+        if let syn::Meta::List(_) = attr.meta {
+            attr.parse_nested_meta(|meta| config.add_nested_meta_item_mod(&meta))
+                .unwrap();
+        }
+
+        let item = syn::parse_str::<syn::Item>(MODULE).unwrap();
+        let syn::Item::Mod(item_mod) = pypropagate_impl(item, &config).unwrap() else {
+            panic!("Expected a module");
+        };
+
+        let mut propagated = Vec::new();
+        collect_propagated_functions(&item_mod.content.unwrap().1, &mut propagated);
+        assert_eq!(propagated, expected);
+    }
+
+    /// Collect the names of functions and methods whose bodies attach the context from Python.
+    fn collect_propagated_functions(items: &[syn::Item], propagated: &mut Vec<String>) {
+        let is_propagated = |block: &syn::Block| {
+            block
+                .to_token_stream()
+                .to_string()
+                .contains("attach_otel_context")
+        };
+        for item in items {
+            match item {
+                syn::Item::Fn(item_fn) if is_propagated(&item_fn.block) => {
+                    propagated.push(item_fn.sig.ident.to_string());
+                }
+                syn::Item::Impl(item_impl) => {
+                    for item in &item_impl.items {
+                        if let syn::ImplItem::Fn(item_method) = item
+                            && is_propagated(&item_method.block)
+                        {
+                            propagated.push(item_method.sig.ident.to_string());
+                        }
+                    }
+                }
+                syn::Item::Mod(item_mod) => {
+                    collect_propagated_functions(&item_mod.content.as_ref().unwrap().1, propagated);
+                }
+                _ => {}
+            }
+        }
+    }
 }
@@ -0,0 +1,74 @@
+// Copyright 2025 Rigetti Computing
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+// http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! Measures the per-call overhead of propagating the `OpenTelemetry` context from Python,
+//! as `#[pypropagate]` does on every call.
+//!
+//! Run with `cargo bench -p pyo3-opentelemetry`. The results depend on whether
+//! `opentelemetry-api` is installed in the Python environment that `PyO3` links against.
+use std::{hint::black_box, time::Instant};
+
+use pyo3::{ffi::c_str, prelude::*};
+use pyo3_opentelemetry::attach_otel_context_from_python;
+
+const ITERATIONS: u32 = 100_000;
+
+/// Run `f` [`ITERATIONS`] times after a warm-up, and print the mean time per call.
+fn bench(name: &str, mut f: impl FnMut()) {
+    for _ in 0..ITERATIONS / 10 {
+        f();
+    }
+    let start = Instant::now();
+    for _ in 0..ITERATIONS {
+        f();
+    }
+    let per_call = start.elapsed() / ITERATIONS;
+    println!("{name:<40} {per_call:>12?} per call");
+}
+
+/// Attach a span with a valid context in Python, using only the `opentelemetry-api` package.
+const ATTACH_SPAN: &std::ffi::CStr = c_str!(
+    r"
+from opentelemetry import context, trace
+
+span_context = trace.SpanContext(
+    trace_id=0x0AF7651916CD43DD8448EB211C80319C,
+    span_id=0xB7AD6B7169203331,
+    is_remote=False,
+    trace_flags=trace.TraceFlags(trace.TraceFlags.SAMPLED),
+)
+token = context.attach(trace.set_span_in_context(trace.NonRecordingSpan(span_context)))
+"
+);
+
+fn main() {
+    Python::initialize();
+    Python::attach(|py| {
+        if py.import("opentelemetry").is_err() {
+            bench("opentelemetry not installed", || {
+                black_box(attach_otel_context_from_python(py).unwrap());
+            });
+            return;
+        }
+
+        bench("no active span", || {
+            black_box(attach_otel_context_from_python(py).unwrap());
+        });
+
+        py.run(ATTACH_SPAN, None, None).unwrap();
+        bench("active span", || {
+            black_box(attach_otel_context_from_python(py).unwrap());
+        });
+    });
+}
@@ -1,9 +1,17 @@
-from typing import Dict, final
+from typing import Callable, Dict, TypeVar, final
 
 from . import _tracing_subscriber as _tracing_subscriber
 
 def example_function() -> Dict[str, str]: ...
 async def example_function_async() -> Dict[str, str]: ...
+def example_function_sync_async() -> Dict[str, str]: ...
+async def example_function_sync_async_async() -> Dict[str, str]: ...
+def example_function_baggage() -> Dict[str, str]: ...
+
+_T = TypeVar("_T")
+
+def example_function_with_callback(callback: Callable[[], _T]) -> _T: ...
+def example_function_with_carrier(carrier: Dict[str, str]) -> Dict[str, str]: ...
 @final
 class ExampleStruct:
     def __new__(cls) -> "ExampleStruct": ...
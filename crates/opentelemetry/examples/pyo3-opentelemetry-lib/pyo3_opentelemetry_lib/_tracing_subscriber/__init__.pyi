@@ -17,6 +17,7 @@ from typing import TYPE_CHECKING, Optional, Type, final
 
 from . import common as common
 from . import layers as layers
+from . import logging as logging
 from . import subscriber as subscriber
 
 class TracingContextManagerError(RuntimeError):
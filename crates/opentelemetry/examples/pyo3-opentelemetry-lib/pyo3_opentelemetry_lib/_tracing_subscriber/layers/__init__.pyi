@@ -14,6 +14,7 @@ from __future__ import annotations
 from typing import TYPE_CHECKING
 
 from . import file as file
+from . import python_logging as python_logging
 from . import otel_otlp_file as otel_otlp_file
 from . import otel_otlp as otel_otlp
 
@@ -22,6 +23,7 @@ if TYPE_CHECKING:
 
   Config = Union[
     file.Config,
+    python_logging.Config,
     otel_otlp_file.Config,
     otel_otlp.Config,
       ]
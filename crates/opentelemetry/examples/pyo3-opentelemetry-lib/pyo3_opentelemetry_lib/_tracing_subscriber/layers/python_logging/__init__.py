@@ -0,0 +1,17 @@
+# *****************************************************************************
+# *                             AUTO-GENERATED CODE                           *
+# *                                                                           *
+# * This code was generated by the `pyo3-tracing-subscriber` crate. Any       *
+# * modifications to this file should be made to the script or the generation *
+# * process that produced this code. Specifically, see:                       *
+# * `pyo3_tracing_subscriber_build::write_stub_files`                        *
+# *                                                                           *
+# * Do not manually edit this file, as your changes may be overwritten the    *
+# * next time the code is generated.                                          *
+# *****************************************************************************
+
+from pyo3_opentelemetry_lib._tracing_subscriber.layers import python_logging
+
+
+__doc__ = python_logging.__doc__
+__all__ = getattr(python_logging, "__all__", [])
@@ -0,0 +1,46 @@
+# *****************************************************************************
+# *                             AUTO-GENERATED CODE                           *
+# *                                                                           *
+# * This code was generated by the `pyo3-tracing-subscriber` crate. Any       *
+# * modifications to this file should be made to the script or the generation *
+# * process that produced this code. Specifically, see:                       *
+# * `pyo3_tracing_subscriber_build::write_stub_files`                        *
+# *                                                                           *
+# * Do not manually edit this file, as your changes may be overwritten the    *
+# * next time the code is generated.                                          *
+# *****************************************************************************
+
+from typing import Optional, final
+
+@final
+class Config:
+    """
+    Configuration for a layer which forwards Rust `tracing` events to a Python `logging.Logger`.
+
+    Each event is logged with the corresponding Python level (`TRACE` events are logged at level 5).
+    The fields of the event and of its enclosing spans are set as attributes of the `LogRecord`, as
+    with `extra`, along with the event's `target`. Fields which conflict with existing `LogRecord`
+    attributes are omitted.
+
+    Events are queued and logged from a dedicated background thread, so that Rust threads never
+    block on the GIL when emitting events. Queued events are flushed when the `Tracing` context
+    manager exits.
+    """
+
+    def __new__(cls, logger_name: Optional[str] = None, filter: Optional[str] = None) -> "Config":
+        """
+        Create a new `Config`.
+
+        :param logger_name: The name of the Python logger to forward events to. If `None`, events
+            are forwarded to the root logger.
+        :param filter: A filter string to use for this layer. This uses the same format as the
+            `tracing_subscriber::filter::EnvFilter
+            <https://docs.rs/tracing-subscriber/latest/tracing_subscriber/filter/struct.EnvFilter.html>`_.
+            In summary, each directive takes the form `target[span{field=value}]=level`, where `target`
+            is roughly the Rust namespace and _only_ `level` is required.
+
+            If not specified, this will first check the `PYO3_OPENTELEMETRY_ENV_FILTER` environment
+            variable and then `RUST_LOG` environment variable. If all of these values are empty, no events
+            will be forwarded.
+        """
+        ...
@@ -0,0 +1,33 @@
+# *****************************************************************************
+# *                             AUTO-GENERATED CODE                           *
+# *                                                                           *
+# * This code was generated by the `pyo3-tracing-subscriber` crate. Any       *
+# * modifications to this file should be made to the script or the generation *
+# * process that produced this code. Specifically, see:                       *
+# * `pyo3_tracing_subscriber_build::write_stub_files`                        *
+# *                                                                           *
+# * Do not manually edit this file, as your changes may be overwritten the    *
+# * next time the code is generated.                                          *
+# *****************************************************************************
+
+import logging
+
+class Handler(logging.Handler):
+    """
+    A `logging.Handler` which forwards Python log records to the tracing subscriber as `tracing`
+    events. The logger name is used as the event target, and any span entered on the Rust side
+    when the record is logged becomes the parent of the event.
+
+    Records are only captured while a tracing subscriber is active, that is, within the `Tracing`
+    context manager. Exception and stack information is included in the event message.
+
+    ```python
+    import logging
+
+    from my_module._tracing_subscriber.logging import Handler
+
+    logging.getLogger().addHandler(Handler())
+    ```
+    """
+
+    def emit(self, record: logging.LogRecord) -> None: ...
@@ -10,16 +10,22 @@
 # * next time the code is generated.                                          *
 # *****************************************************************************
 
-from typing import final
+from typing import Sequence, final
 
 from .. import layers
 
 @final
 class Config:
     """
-    Configuration for the tracing subscriber. Currently, this only requires a single layer to be
-    set on the `tracing_subscriber::Registry`.
+    Configuration for the tracing subscriber. Each of the configured layers is set on a single
+    `tracing_subscriber::Registry`.
+
+    Specify either a single `layer` or a non-empty sequence of `layers`, but not both. If neither
+    is specified, the subscriber uses the default `layers.file.Config`. On context manager exit,
+    layers are shut down in the order given.
     """
 
-    def __new__(cls, layer: layers.Config | None = None) -> "Config": ...
+    def __new__(
+        cls, layer: layers.Config | None = None, layers: Sequence[layers.Config] | None = None
+    ) -> "Config": ...
 
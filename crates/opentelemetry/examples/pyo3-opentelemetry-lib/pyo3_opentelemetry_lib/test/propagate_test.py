@@ -14,13 +14,34 @@
 #    limitations under the License.
 ##############################################################################
 
+import os
+from time import time
+from typing import Any, Dict, Tuple
+
 import pytest
-from opentelemetry import propagate
+from opentelemetry import baggage, propagate
 from opentelemetry.context import attach, detach
 from opentelemetry.trace import Tracer
 from opentelemetry.trace.propagation import get_current_span
 
 import pyo3_opentelemetry_lib
+from pyo3_opentelemetry_lib._tracing_subscriber import CurrentThreadTracingConfig, SimpleConfig, Tracing, subscriber
+from pyo3_opentelemetry_lib._tracing_subscriber.layers import otel_otlp_file as file
+
+from .tracing_test import _TEST_ARTIFACTS_DIR, _read_file_spans
+
+
+def _rust_tracing(filename: str) -> Tracing:
+    """
+    Export Rust spans on the current thread to a file, so that they have their own span IDs.
+    """
+    return Tracing(
+        config=CurrentThreadTracingConfig(
+            export_process=SimpleConfig(
+                subscriber=subscriber.Config(layer=file.Config(file_path=os.path.join(_TEST_ARTIFACTS_DIR, filename)))
+            )
+        )
+    )
 
 
 def test_function_context_propagation(tracer: Tracer) -> None:
@@ -40,11 +61,110 @@ def test_function_context_propagation(tracer: Tracer) -> None:
 @pytest.mark.asyncio
 async def test_async_function_context_propagation(tracer: Tracer) -> None:
     with tracer.start_as_current_span("test_async_function_context_propagation"):
-        current_span = get_current_span()
-        trace_id = current_span.get_span_context().trace_id
+        span_context = get_current_span().get_span_context()
+        # This is a `#[pypropagate] async fn`, whose context is extracted when it's first polled.
         result = await pyo3_opentelemetry_lib.example_function_async()
 
-    assert get_current_span().get_span_context().trace_id != trace_id
+    assert get_current_span().get_span_context().trace_id != span_context.trace_id
+
+    # Without a Rust OpenTelemetry layer, the context within Rust is the caller's, which is
+    # therefore the parent of any span started there, even after an `.await`.
+    propagated = get_current_span(propagate.extract(carrier=result)).get_span_context()
+    assert propagated.trace_id == span_context.trace_id
+    assert propagated.span_id == span_context.span_id
+
+
+def test_sync_async_function_context_propagation(tracer: Tracer) -> None:
+    with tracer.start_as_current_span("test_sync_async_function_context_propagation"):
+        span_context = get_current_span().get_span_context()
+        result = pyo3_opentelemetry_lib.example_function_sync_async()
+
+    # Without a Rust OpenTelemetry layer, the context within Rust is the caller's, which is
+    # therefore the parent of any span started there.
+    propagated = get_current_span(propagate.extract(carrier=result)).get_span_context()
+    assert propagated.trace_id == span_context.trace_id
+    assert propagated.span_id == span_context.span_id
+
+
+@pytest.mark.asyncio
+async def test_sync_async_function_async_context_propagation(tracer: Tracer) -> None:
+    with tracer.start_as_current_span("test_sync_async_function_async_context_propagation"):
+        span_context = get_current_span().get_span_context()
+        result = await pyo3_opentelemetry_lib.example_function_sync_async_async()
+
+    propagated = get_current_span(propagate.extract(carrier=result)).get_span_context()
+    assert propagated.trace_id == span_context.trace_id
+    assert propagated.span_id == span_context.span_id
+
+
+def test_baggage_propagation() -> None:
+    token = attach(baggage.set_baggage("yak", "shaved"))
+    try:
+        result = pyo3_opentelemetry_lib.example_function_baggage()
+    finally:
+        detach(token)
+
+    assert result == {"yak": "shaved"}
+
+
+def test_rust_to_python_context_propagation(tracer: Tracer, file_export_filter: None) -> None:
+    def callback() -> Tuple[int, int, int]:
+        rust_span_id = get_current_span().get_span_context().span_id
+        with tracer.start_as_current_span("callback") as span:
+            return span.get_span_context().trace_id, rust_span_id, span.parent.span_id
+
+    filename = f"test_rust_to_python_context_propagation-{time()}.txt"
+    with _rust_tracing(filename):
+        with tracer.start_as_current_span("test_rust_to_python_context_propagation"):
+            span_context = get_current_span().get_span_context()
+            span_id = span_context.span_id
+            trace_id, rust_span_id, callback_parent_span_id = pyo3_opentelemetry_lib.example_function_with_callback(
+                callback
+            )
+            # The context attached by Rust is detached after the callback returns.
+            assert get_current_span().get_span_context().span_id == span_id
+
+    # The span started in Python is a child of the Rust span, rather than of the calling span.
+    assert trace_id == span_context.trace_id
+    assert callback_parent_span_id == rust_span_id
+    assert rust_span_id != span_id
+
+    # The Rust span, created by `#[pypropagate(span)]`, is a child of the calling span.
+    rust_span = _file_spans_by_id(filename)[rust_span_id]
+    assert rust_span["name"] == "example_function_with_callback"
+    assert int(rust_span["parentSpanId"], 16) == span_id
+
+
+def test_method_span_parentage(tracer: Tracer, file_export_filter: None) -> None:
+    filename = f"test_method_span_parentage-{time()}.txt"
+    with _rust_tracing(filename):
+        example_struct = pyo3_opentelemetry_lib.ExampleStruct()
+        with tracer.start_as_current_span("test_method_span_parentage"):
+            span_id = get_current_span().get_span_context().span_id
+            example_struct.example_method()
+            # The class is looked up once, so calling the method again records the same one.
+            example_struct.example_method()
+
+    method_spans = [span for span in _file_spans_by_id(filename).values() if span["name"] == "ExampleStruct.example_method"]
+    assert len(method_spans) == 2
+    for method_span in method_spans:
+        assert int(method_span["parentSpanId"], 16) == span_id
+        attributes = {attribute["key"]: attribute["value"] for attribute in method_span["attributes"]}
+        assert attributes["python.qualname"] == {"stringValue": "ExampleStruct.example_method"}
+        assert attributes["python.module"] == {"stringValue": "pyo3_opentelemetry_lib"}
+
+
+def _file_spans_by_id(filename: str) -> Dict[int, Dict[str, Any]]:
+    return {int(span["spanId"], 16): span for span in _read_file_spans(filename)}
+
+
+def test_carrier_context_propagation(tracer: Tracer) -> None:
+    with tracer.start_as_current_span("test_carrier_context_propagation"):
+        trace_id = get_current_span().get_span_context().trace_id
+        carrier: dict = {}
+        propagate.inject(carrier)
+
+    result = pyo3_opentelemetry_lib.example_function_with_carrier(carrier)
 
     new_context = propagate.extract(carrier=result)
     token = attach(new_context)
@@ -163,6 +163,7 @@ async def test_file_export_asynchronous(
             assert span_context.is_valid
             trace_id = span_context.trace_id
             assert trace_id != 0
+            span_id = span_context.span_id
             # This function is implemented and instrumented in `examples/pyo3-opentelemetry-lib/src/lib.rs`.
             result = await pyo3_opentelemetry_lib.example_function_async()
 
@@ -183,6 +184,8 @@ async def test_file_export_asynchronous(
                 span_trace_id = int(span["traceId"], 16)
                 assert span_trace_id is None or span_trace_id == trace_id, filename
                 if span["name"] == "example_function_impl_async":
+                    # The context from Python is attached to the `#[pypropagate] async fn`.
+                    assert int(span["parentSpanId"], 16) == span_id
                     duration_ns = int(span["endTimeUnixNano"]) - int(span["startTimeUnixNano"])
                     expected_duration_ms = 100
                     assert duration_ns > (expected_duration_ms * 10**6)
@@ -194,6 +197,62 @@ async def test_file_export_asynchronous(
     assert counter["example_function_impl_async"] == 1
 
 
+_TEST_FILE_EXPORT_SYNC_ASYNC = [
+    global_tracing(
+        lambda filename: GlobalTracingConfig(
+            export_process=SimpleConfig(
+                subscriber=subscriber.Config(layer=file.Config(file_path=os.path.join(_TEST_ARTIFACTS_DIR, filename)))
+            )
+        )
+    ),
+]
+
+
+@pytest.mark.parametrize(
+    "config_builder",
+    _TEST_FILE_EXPORT_SYNC_ASYNC,
+    ids=[str(i).zfill(2) for i in range(len(_TEST_FILE_EXPORT_SYNC_ASYNC))],
+)
+async def test_file_export_sync_async(
+    config_builder: Callable[[str], TracingConfig], tracer: Tracer, file_export_filter: None
+):
+    """
+    Test that spans started within both variants of a `py_function_sync_async!` function are
+    children of the calling Python span, even though they run on the Tokio runtime.
+    """
+    filename = f"test_file_export_sync_async-{time()}.txt"
+    config = config_builder(filename)
+    with Tracing(config=config):
+        with tracer.start_as_current_span("test_file_export_sync"):
+            sync_span_id = get_current_span().get_span_context().span_id
+            # This function is implemented and instrumented in `examples/pyo3-opentelemetry-lib/src/lib.rs`.
+            sync_result = pyo3_opentelemetry_lib.example_function_sync_async()
+        with tracer.start_as_current_span("test_file_export_async"):
+            async_span_id = get_current_span().get_span_context().span_id
+            async_result = await pyo3_opentelemetry_lib.example_function_sync_async_async()
+
+    spans = {span["spanId"]: span for span in _read_file_spans(filename)}
+    for result, parent_span_id in ((sync_result, sync_span_id), (async_result, async_span_id)):
+        span_id = get_current_span(propagate.extract(carrier=result)).get_span_context().span_id
+        span = spans[format(span_id, "016x")]
+        assert span["name"] == "example_function_impl"
+        assert int(span["parentSpanId"], 16) == parent_span_id
+
+
+def _read_file_spans(filename: str) -> List[Dict[str, Any]]:
+    """
+    Read the OTLP spans written to file by the `otel_otlp_file` layer.
+    """
+    file_path = os.path.join(_TEST_ARTIFACTS_DIR, filename)
+    spans: List[Dict[str, Any]] = []
+    with open(file_path, "r") as f:
+        for line in f.readlines():
+            for resource_span in json.loads(line)["resourceSpans"]:
+                for scoped_span in resource_span["scopeSpans"]:
+                    spans += scoped_span["spans"]
+    return spans
+
+
 _TEST_OTLP_EXPORT = [
     CurrentThreadTracingConfig(export_process=SimpleConfig(subscriber=subscriber.Config(layer=otlp.Config()))),
     CurrentThreadTracingConfig(export_process=BatchConfig(subscriber=subscriber.Config(layer=otlp.Config()))),
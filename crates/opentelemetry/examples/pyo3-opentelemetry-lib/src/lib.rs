@@ -19,10 +19,11 @@
 use std::collections::HashMap;
 
 use pyo3::{prelude::*, types::PyDict};
-use pyo3_opentelemetry::pypropagate;
+use pyo3_opentelemetry::{PyOtelContext, pypropagate};
+use qcs_dependencies_client::opentelemetry::baggage::BaggageExt;
 use qcs_dependencies_client::opentelemetry::propagation::TextMapPropagator;
 use qcs_dependencies_client::opentelemetry::trace::FutureExt;
-use rigetti_pyo3::sync::Awaitable;
+use rigetti_pyo3::{py_function_sync_async, sync::Awaitable};
 use tracing::instrument;
 
 #[instrument]
@@ -50,26 +51,83 @@ pub fn example_function(py: Python<'_>) -> HashMap<String, String> {
 
 /// An example async function that will call a function containing and span and returns a
 /// [`HashMap`] with the propagated OTel context.
+///
+/// # Errors
+///
+/// Returns a `RuntimeError` if the Tokio task panics.
 #[pypropagate]
 #[pyfunction]
-pub fn example_function_async<'py>(py: Python<'py>) -> PyResult<Bound<'py, PyAny>> {
-    pyo3_async_runtimes::tokio::future_into_py(
-        py,
-        example_function_impl_async().with_current_context(),
-    )
+pub async fn example_function_async() -> PyResult<HashMap<String, String>> {
+    // `PyO3` doesn't poll the future within a Tokio runtime, so the work is spawned onto one,
+    // along with the context from Python.
+    pyo3_async_runtimes::tokio::get_runtime()
+        .spawn(example_function_impl_async().with_current_context())
+        .await
+        .map_err(|_| pyo3::exceptions::PyRuntimeError::new_err("the task panicked"))?
+}
+
+py_function_sync_async! {
+    /// An example function, exported with both sync and async variants, that will call a function
+    /// containing a span and returns a [`HashMap`] with the propagated OTel context.
+    #[pyfunction]
+    async fn example_function_sync_async() -> PyResult<HashMap<String, String>> {
+        Ok(example_function_impl())
+    }
+}
+
+/// An example function that returns the W3C Baggage propagated from the calling Python context.
+#[pypropagate(on_context_extraction_failure = "warn_once")]
+#[pyfunction]
+pub fn example_function_baggage() -> HashMap<String, String> {
+    qcs_dependencies_client::opentelemetry::Context::map_current(|context| {
+        context
+            .baggage()
+            .iter()
+            .map(|(key, (value, _))| (key.to_string(), value.to_string()))
+            .collect()
+    })
+}
+
+/// An example function that calls `callback` within a Rust span, which is attached to Python so that
+/// spans started by `callback` are its children, and returns what `callback` returns.
+///
+/// The span is created by `span`, rather than `#[instrument]`, which would enter its span before
+/// the context from Python is attached.
+#[pypropagate(direction = "both", span)]
+#[pyfunction]
+pub fn example_function_with_callback<'py>(
+    callback: &Bound<'py, PyAny>,
+) -> PyResult<Bound<'py, PyAny>> {
+    callback.call0()
+}
+
+/// An example function that attaches the OTel context from a carrier populated in Python, on
+/// another thread, and returns a [`HashMap`] with the propagated OTel context.
+///
+/// # Errors
+///
+/// Returns a `RuntimeError` if the thread panics.
+#[pyfunction]
+pub fn example_function_with_carrier(carrier: PyOtelContext) -> PyResult<HashMap<String, String>> {
+    std::thread::spawn(move || {
+        let _guard = carrier.attach();
+        example_function_impl()
+    })
+    .join()
+    .map_err(|_| pyo3::exceptions::PyRuntimeError::new_err("the thread panicked"))
 }
 
 /// An example pyclass sturct that will have methods that propagate their OTel contexts from
-/// the calling Python context.
-#[pyclass]
+/// the calling Python context, each within a span named after the method.
+#[pyclass(module = "pyo3_opentelemetry_lib")]
 #[derive(Debug)]
 pub struct ExampleStruct;
 
-#[pypropagate]
+#[pypropagate(span)]
 #[pymethods]
 impl ExampleStruct {
     #[new]
-    fn new(py: Python<'_>) -> Self {
+    fn new() -> Self {
         Self
     }
 
@@ -95,6 +153,11 @@ fn pyo3_opentelemetry_lib<'py>(py: Python<'py>, m: &Bound<'py, PyModule>) -> PyR
     m.add_class::<ExampleStruct>()?;
     m.add_function(wrap_pyfunction!(example_function, m)?)?;
     m.add_function(wrap_pyfunction!(example_function_async, m)?)?;
+    m.add_function(wrap_pyfunction!(py_example_function_sync_async, m)?)?;
+    m.add_function(wrap_pyfunction!(py_example_function_sync_async_async, m)?)?;
+    m.add_function(wrap_pyfunction!(example_function_baggage, m)?)?;
+    m.add_function(wrap_pyfunction!(example_function_with_callback, m)?)?;
+    m.add_function(wrap_pyfunction!(example_function_with_carrier, m)?)?;
 
     pyo3_tracing_subscriber::add_submodule("pyo3_opentelemetry_lib", "_tracing_subscriber", py, m)?;
     Ok(())
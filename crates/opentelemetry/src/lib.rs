@@ -13,12 +13,15 @@
 // limitations under the License.
 
 //! This crate provides a function for attaching an `OpenTelemetry` context from Python within
-//! Rust. It is intended to be used in conjunction with the `pypropagate` macro, which is
-//! re-exported here.
+//! Rust, and another for attaching the context from Rust within Python. It is intended to be used
+//! in conjunction with the `pypropagate` macro, which is re-exported here.
 //!
 //! # Requirements and Limitations
 //!
 //! * All functionality here requires the calling Python code to have [opentelemetry-api](https://pypi.org/project/opentelemetry-api/) to be installed.
+//!   If it isn't, propagation is a no-op: a single `RuntimeWarning` is emitted with Python's
+//!   `warnings` module the first time a context is propagated, which can be silenced with
+//!   `warnings.filterwarnings`, and the package isn't searched for again.
 //! * See `pypropagate` for additional requirements and limitations.
 //!
 //! # Related Crates
@@ -41,6 +44,24 @@
 //!   println!("span \"my_function\" is active and will share the Python OpenTelemetry context");
 //! }
 //!
+//! // `#[instrument]` would create its span before the context from Python is attached,
+//! // so async functions use `span` to create one within it.
+//! #[pypropagate(span)]
+//! async fn my_async_function(count: u32) -> PyResult<u32> {
+//!   println!("span \"my_async_function\" shares the Python OpenTelemetry context across awaits");
+//!   let count = async { count.checked_add(1) }.await.ok_or_else(|| {
+//!     pyo3::exceptions::PyOverflowError::new_err("count is too large")
+//!   })?;
+//!   Ok(count)
+//! }
+//!
+//! // `span` creates a span within the Python context, instead of pairing with `#[instrument]`.
+//! #[pypropagate(span(level = "debug", fields(count)))]
+//! #[pyfunction]
+//! fn my_spanned_function(count: u32) -> u32 {
+//!   count + 1
+//! }
+//!
 //! #[pymodule]
 //! fn my_module(m: &Bound<'_, PyModule>) -> PyResult<()> {
 //!    m.add_function(wrap_pyfunction!(my_function, m)?)?;
@@ -48,75 +69,295 @@
 //! }
 //! ```
 //!
+//! `pypropagate` can also be applied to a whole module, to propagate the context to every
+//! `#[pyfunction]` and `#[pymethods]` method within it, optionally selected by patterns of their
+//! Python qualified names:
+//!
+//! ```
+//! use pyo3_opentelemetry::pypropagate;
+//!
+//! #[pypropagate(exclude("Yak.__*__"))]
+//! mod yaks {
+//!     use pyo3::prelude::*;
+//!
+//!     #[pyclass]
+//!     pub struct Yak;
+//!
+//!     #[pymethods]
+//!     impl Yak {
+//!         fn shave(&self) {}
+//!
+//!         fn __repr__(&self) -> String {
+//!             "Yak".to_string()
+//!         }
+//!     }
+//!
+//!     #[pyfunction]
+//!     fn shave_all() {}
+//! }
+//! ```
+//!
 //! For a more comprehensive example, see the `pyo3-opentelemetry-lib` example in this repository.
 //! Specifically, see the `pyo3-opentelemetry-lib/src/lib.rs` for the Rust code and `pyo3-opentelemetry-lib/pyo3_opentelemetry_lib/tests/test_tracing.py` for the Python code and behavioural assertions.
-use std::collections::HashMap;
+use std::{
+    collections::HashMap,
+    ffi::{CStr, CString},
+    marker::PhantomData,
+    sync::{Arc, LazyLock, PoisonError, RwLock},
+};
 
-use pyo3::{prelude::*, types::IntoPyDict};
+use pyo3::{
+    PyTypeInfo,
+    exceptions::{PyModuleNotFoundError, PyRuntimeWarning},
+    prelude::*,
+    sync::PyOnceLock,
+    types::{IntoPyDict, PyDict},
+};
 
 use qcs_dependencies_client::{
     opentelemetry::{
         Context, ContextGuard,
-        propagation::{Extractor, TextMapPropagator},
+        propagation::{Extractor, TextMapCompositePropagator, TextMapPropagator},
     },
-    opentelemetry_sdk::propagation::TraceContextPropagator,
+    opentelemetry_sdk::propagation::{BaggagePropagator, TraceContextPropagator},
 };
 
 pub use pyo3_opentelemetry_macros::pypropagate;
 
+#[doc(hidden)]
+pub use pyo3 as __pyo3;
 #[doc(hidden)]
 pub use qcs_dependencies_client::opentelemetry as __opentelemetry;
+#[doc(hidden)]
+pub use tracing as __tracing;
 
 /// A context carrier for propagating `OpenTelemetry` context from Python to Rust.
-#[derive(Default, Clone, Debug, FromPyObject)]
-struct Carrier {
-    /// The context [traceparent](https://www.w3.org/TR/trace-context/#traceparent-header).
-    #[pyo3(item)]
-    traceparent: Option<String>,
-    /// The context [tracestate](https://www.w3.org/TR/trace-context/#tracestate-header).
-    #[pyo3(item)]
-    tracestate: Option<String>,
-}
-
-const TRACEPARENT_HEADER: &str = "traceparent";
-const TRACESTATE_HEADER: &str = "tracestate";
+///
+/// This holds every key that the Python propagators injected, so whichever formats they use
+/// (W3C `TraceContext`, W3C Baggage, B3, Jaeger, ...) are available to the Rust propagator.
+#[derive(Default, Clone, Debug)]
+struct Carrier(HashMap<String, String>);
 
 impl Extractor for Carrier {
     fn get(&self, key: &str) -> Option<&str> {
-        match key.to_lowercase().as_str() {
-            TRACEPARENT_HEADER => self.traceparent.as_deref(),
-            TRACESTATE_HEADER => self.tracestate.as_deref(),
-            _ => None,
-        }
+        self.0.get(&key.to_lowercase()).map(String::as_str)
     }
 
     fn keys(&self) -> Vec<&str> {
-        vec![TRACEPARENT_HEADER, TRACESTATE_HEADER]
+        self.0.keys().map(String::as_str).collect()
     }
 }
 
 impl From<HashMap<String, String>> for Carrier {
     fn from(value: HashMap<String, String>) -> Self {
-        Self {
-            tracestate: value.get(TRACESTATE_HEADER).cloned(),
-            traceparent: value.get(TRACEPARENT_HEADER).cloned(),
-        }
+        // Header names are case-insensitive, so normalize them for lookups.
+        Self(
+            value
+                .into_iter()
+                .map(|(key, value)| (key.to_lowercase(), value))
+                .collect(),
+        )
     }
 }
 
 impl Carrier {
-    /// When a `Propagator` is passed to a function or method, this method should be called
-    /// at the beginning of the function or method to attach the context. This should not be used with
-    /// async functions.
-    fn attach(&self) -> ContextGuard {
-        let propagator = TraceContextPropagator::new();
-        Context::attach(propagator.extract(self))
+    /// Extract the `OpenTelemetry` context carried by this carrier.
+    fn extract(&self, propagator: &dyn TextMapPropagator) -> Context {
+        propagator.extract(self)
+    }
+}
+
+/// The propagator used to extract contexts from Python; see [`set_propagator`].
+static PROPAGATOR: LazyLock<RwLock<Arc<dyn TextMapPropagator + Send + Sync>>> =
+    LazyLock::new(|| RwLock::new(Arc::new(default_propagator())));
+
+/// The propagator used when none has been set: W3C `TraceContext` and W3C Baggage,
+/// matching the default `OTEL_PROPAGATORS` used by the Python SDK.
+fn default_propagator() -> TextMapCompositePropagator {
+    TextMapCompositePropagator::new(vec![
+        Box::new(TraceContextPropagator::new()),
+        Box::new(BaggagePropagator::new()),
+    ])
+}
+
+/// Set the propagator used to extract the `OpenTelemetry` context passed from Python,
+/// both by [`attach_otel_context_from_python`] and by functions annotated with `pypropagate`.
+///
+/// The context is passed from Python by injecting it with `opentelemetry.propagate.inject`,
+/// which uses the propagators configured in Python (e.g. with `OTEL_PROPAGATORS`), and then
+/// extracting it in Rust with this propagator, so the two should understand the same formats.
+/// By default, W3C `TraceContext` and W3C Baggage are extracted. Use a
+/// [`TextMapCompositePropagator`] to extract several formats at once.
+///
+/// # Examples
+///
+/// ```rust
+/// use pyo3_opentelemetry::set_propagator;
+/// use qcs_dependencies_client::{
+///     opentelemetry::propagation::TextMapCompositePropagator,
+///     opentelemetry_sdk::propagation::{BaggagePropagator, TraceContextPropagator},
+/// };
+///
+/// set_propagator(TextMapCompositePropagator::new(vec![
+///     Box::new(TraceContextPropagator::new()),
+///     Box::new(BaggagePropagator::new()),
+///     // Add e.g. a B3 or Jaeger propagator here.
+/// ]));
+/// ```
+pub fn set_propagator<P>(propagator: P)
+where
+    P: TextMapPropagator + Send + Sync + 'static,
+{
+    *PROPAGATOR.write().unwrap_or_else(PoisonError::into_inner) = Arc::new(propagator);
+}
+
+/// The propagator set with [`set_propagator`], or the default.
+fn current_propagator() -> Arc<dyn TextMapPropagator + Send + Sync> {
+    PROPAGATOR
+        .read()
+        .unwrap_or_else(PoisonError::into_inner)
+        .clone()
+}
+
+/// The functions from the Python `opentelemetry` package that are used to propagate contexts.
+///
+/// These are imported once and cached, since importing them on every call is a significant
+/// part of the cost of propagating a context.
+struct PythonOtelApi {
+    /// `opentelemetry.context.get_current`
+    get_current: Py<PyAny>,
+    /// `opentelemetry.context.attach`
+    attach: Py<PyAny>,
+    /// `opentelemetry.context.detach`
+    detach: Py<PyAny>,
+    /// `opentelemetry.propagate.inject`
+    inject: Py<PyAny>,
+    /// `opentelemetry.propagate.extract`
+    extract: Py<PyAny>,
+}
+
+/// The cached [`PythonOtelApi`], which is `None` if the `opentelemetry` package isn't installed,
+/// so that a missing package isn't searched for again on every call. Any other import error isn't
+/// cached, since it may be transient, e.g. a `KeyboardInterrupt` or a circular import.
+static PYTHON_OTEL_API: PyOnceLock<Option<PythonOtelApi>> = PyOnceLock::new();
+
+const WARNING_OPENTELEMETRY_NOT_INSTALLED: &CStr = c"the Python opentelemetry-api package is not \
+installed, so OpenTelemetry contexts will not be propagated between Python and Rust";
+
+impl PythonOtelApi {
+    /// Import the functions from the Python `opentelemetry` package, or return `None`
+    /// (after warning about it) if the package isn't installed.
+    fn import_if_installed(py: Python<'_>) -> PyResult<Option<Self>> {
+        match Self::import(py) {
+            Ok(api) => Ok(Some(api)),
+            Err(error) if is_opentelemetry_not_found(py, &error) => {
+                warn(py, WARNING_OPENTELEMETRY_NOT_INSTALLED);
+                Ok(None)
+            }
+            Err(error) => Err(error),
+        }
+    }
+
+    /// Import the functions from the Python `opentelemetry` package.
+    fn import(py: Python<'_>) -> PyResult<Self> {
+        let context = py.import("opentelemetry.context")?;
+        let propagate = py.import("opentelemetry.propagate")?;
+        Ok(Self {
+            get_current: context.getattr("get_current")?.unbind(),
+            attach: context.getattr("attach")?.unbind(),
+            detach: context.getattr("detach")?.unbind(),
+            inject: propagate.getattr("inject")?.unbind(),
+            extract: propagate.getattr("extract")?.unbind(),
+        })
+    }
+
+    /// Get the cached functions, importing them on first use, or until an import succeeds
+    /// or finds that the package isn't installed. Returns `None` if it isn't installed.
+    fn get(py: Python<'_>) -> PyResult<Option<&'static Self>> {
+        PYTHON_OTEL_API
+            .get_or_try_init(py, || Self::import_if_installed(py))
+            .map(Option::as_ref)
+    }
+}
+
+/// Whether `error` was raised because the `opentelemetry` package itself isn't installed,
+/// as opposed to e.g. one of its dependencies being missing.
+fn is_opentelemetry_not_found(py: Python<'_>, error: &PyErr) -> bool {
+    error.is_instance_of::<PyModuleNotFoundError>(py)
+        && error
+            .value(py)
+            .getattr("name")
+            .and_then(|name| name.extract::<String>())
+            .is_ok_and(|name| name == "opentelemetry")
+}
+
+/// Emit a `RuntimeWarning` with Python's `warnings` module. If the warning is turned into an
+/// exception, e.g. with `-W error`, it's reported as unraisable rather than returned.
+fn warn(py: Python<'_>, message: &CStr) {
+    let category = py.get_type::<PyRuntimeWarning>();
+    if let Err(error) = PyErr::warn(py, &category, message, 1) {
+        error.write_unraisable(py, None);
     }
 }
 
+/// The Python name and module of a `#[pyclass]`, for the span created by
+/// `#[pypropagate(span)]` on its methods.
+#[doc(hidden)]
+#[derive(Debug)]
+pub struct __PythonType {
+    /// The class name, following `#[pyclass(name = "...")]`.
+    pub name: String,
+    /// The module, or an empty string if it can't be found.
+    pub module: String,
+}
+
+/// Look up the Python name and module of `T`.
+///
+/// They're looked up once and stored in `python_type`, a static declared by each method.
+#[doc(hidden)]
+#[must_use]
+pub fn __python_type_of<T: PyTypeInfo>(
+    python_type: &'static PyOnceLock<__PythonType>,
+) -> &'static __PythonType {
+    Python::attach(|py| {
+        python_type.get_or_init(py, || {
+            let type_object = T::type_object(py);
+            __PythonType {
+                name: type_object
+                    .name()
+                    .map(|name| name.to_string())
+                    .unwrap_or_default(),
+                module: type_object
+                    .module()
+                    .map(|module| module.to_string())
+                    .unwrap_or_default(),
+            }
+        })
+    })
+}
+
+/// Warn about `error` with Python's `warnings` module.
+///
+/// This is used by `#[pypropagate(on_context_extraction_failure = "warn_once")]`,
+/// which only calls it the first time propagating a context fails for each function.
+#[doc(hidden)]
+pub fn __warn_context_propagation_failure(error: &PyErr) {
+    Python::attach(|py| {
+        let message = format!("failed to propagate the OpenTelemetry context: {error}");
+        // Python strings can't contain a nul byte, so this only fails if the error's does.
+        if let Ok(message) = CString::new(message) {
+            warn(py, &message);
+        }
+    });
+}
+
 /// Attach the current `OpenTelemetry` context from Python. This should be called at the beginning of
 /// a function or method to attach the context. This should not be used with async functions.
 ///
+/// The Python `opentelemetry` functions are imported once and cached, and when Python has no
+/// active span or baggage, this returns without propagating anything, so it's cheap to call on
+/// hot paths. See the `propagation` benchmark for the per-call overhead.
+///
 /// # Requirements and Limitations
 ///
 /// * The calling Python code must have [opentelemetry-api](https://pypi.org/project/opentelemetry-api/) installed.
@@ -140,18 +381,239 @@ impl Carrier {
 ///
 /// Any Python error that occurs while trying to get the current context from Python will
 /// be returned; this includes import errors when importing `opentelemetry.context` and
-/// `opentelemetry.propagate`.
+/// `opentelemetry.propagate`, other than the `opentelemetry` package not being installed,
+/// in which case the current Rust context is used instead.
 pub fn attach_otel_context_from_python(py: Python<'_>) -> PyResult<ContextGuard> {
-    let get_current_context = py.import("opentelemetry.context")?.getattr("get_current")?;
-    let inject = py.import("opentelemetry.propagate")?.getattr("inject")?;
+    otel_context_from_python(py).map(Context::attach)
+}
+
+/// Get the current `OpenTelemetry` context from Python, without attaching it.
+///
+/// This is useful for async functions, which can't hold a [`ContextGuard`] across `.await` points;
+/// instead, attach the context to the future with
+/// [`FutureExt::with_context`](qcs_dependencies_client::opentelemetry::trace::FutureExt::with_context).
+/// The `pypropagate` macro does this for `async fn`s.
+///
+/// # Examples
+///
+/// ```rust
+/// use pyo3::prelude::*;
+/// use pyo3_opentelemetry::otel_context_from_python;
+/// use qcs_dependencies_client::opentelemetry::trace::FutureExt;
+///
+/// async fn my_async_function() {
+///     println!("the Python OpenTelemetry context is attached while this runs");
+/// }
+///
+/// fn my_function(py: Python<'_>) -> PyResult<impl Future<Output = ()>> {
+///     let context = otel_context_from_python(py)?;
+///     Ok(my_async_function().with_context(context))
+/// }
+/// ```
+///
+/// # Errors
+///
+/// See [`attach_otel_context_from_python`].
+pub fn otel_context_from_python(py: Python<'_>) -> PyResult<Context> {
+    otel_context_from_python_with_propagator(py, current_propagator().as_ref())
+}
 
-    let current_context = get_current_context.call0()?;
-    let data = pyo3::types::PyDict::new(py).into_any();
-    let kwargs = [("context", current_context), ("carrier", data.clone())].into_py_dict(py)?;
-    inject.call((), Some(&kwargs))?;
+/// Get the current `OpenTelemetry` context from Python using the given propagator,
+/// instead of the one set with [`set_propagator`].
+///
+/// # Errors
+///
+/// See [`attach_otel_context_from_python`].
+pub fn otel_context_from_python_with_propagator(
+    py: Python<'_>,
+    propagator: &dyn TextMapPropagator,
+) -> PyResult<Context> {
+    let Some(api) = PythonOtelApi::get(py)? else {
+        return Ok(Context::current());
+    };
+
+    // Without an active span or any baggage, there's nothing to propagate.
+    let current_context = api.get_current.bind(py).call0()?;
+    if current_context.is_empty()? {
+        return Ok(Context::current());
+    }
+
+    let data = PyDict::new(py);
+    api.inject.bind(py).call1((&data, current_context))?;
 
     let data: HashMap<String, String> = data.extract()?;
     let carrier: Carrier = data.into();
 
-    Ok(carrier.attach())
+    Ok(carrier.extract(propagator))
+}
+
+/// An `OpenTelemetry` context captured from Python, which can be attached later.
+///
+/// Unlike [`attach_otel_context_from_python`], which attaches the context immediately,
+/// this captures the context so that it can be sent to, and attached in, e.g. a spawned task
+/// or a thread pool, where there's no Python context to propagate from.
+///
+/// It can also be extracted from a function argument, from a `dict` carrier that Python
+/// populated with `opentelemetry.propagate.inject`, which is extracted with the propagator set
+/// with [`set_propagator`].
+///
+/// # Examples
+///
+/// ```rust
+/// use pyo3::prelude::*;
+/// use pyo3_opentelemetry::PyOtelContext;
+///
+/// fn process(context: PyOtelContext) {
+///     let _guard = context.attach();
+///     println!("the Python OpenTelemetry context is attached while this runs");
+/// }
+///
+/// #[pyfunction]
+/// fn process_in_background(py: Python<'_>) -> PyResult<()> {
+///     let context = PyOtelContext::from_python(py)?;
+///     std::thread::spawn(move || process(context));
+///     Ok(())
+/// }
+///
+/// /// Called from Python with a carrier populated by `opentelemetry.propagate.inject`.
+/// #[pyfunction]
+/// fn process_from_carrier(carrier: PyOtelContext) {
+///     process(carrier);
+/// }
+/// ```
+#[derive(Clone, Debug, Default)]
+pub struct PyOtelContext(Context);
+
+impl PyOtelContext {
+    /// Capture the current `OpenTelemetry` context from Python.
+    ///
+    /// # Errors
+    ///
+    /// See [`attach_otel_context_from_python`].
+    pub fn from_python(py: Python<'_>) -> PyResult<Self> {
+        otel_context_from_python(py).map(Self)
+    }
+
+    /// Attach the captured context, until the returned guard is dropped.
+    #[must_use = "the context is detached when the guard is dropped"]
+    pub fn attach(&self) -> ContextGuard {
+        self.0.clone().attach()
+    }
+
+    /// The captured context.
+    #[must_use]
+    pub const fn context(&self) -> &Context {
+        &self.0
+    }
+}
+
+impl From<PyOtelContext> for Context {
+    fn from(context: PyOtelContext) -> Self {
+        context.0
+    }
+}
+
+impl From<Context> for PyOtelContext {
+    fn from(context: Context) -> Self {
+        Self(context)
+    }
+}
+
+impl From<HashMap<String, String>> for PyOtelContext {
+    /// Extract the context from a carrier with the propagator set with [`set_propagator`].
+    fn from(carrier: HashMap<String, String>) -> Self {
+        Self(Carrier::from(carrier).extract(current_propagator().as_ref()))
+    }
+}
+
+impl<'py> FromPyObject<'_, 'py> for PyOtelContext {
+    type Error = PyErr;
+
+    fn extract(obj: Borrowed<'_, 'py, PyAny>) -> Result<Self, Self::Error> {
+        obj.extract::<HashMap<String, String>>().map(Self::from)
+    }
+}
+
+/// Attach the current `OpenTelemetry` context from Rust to Python.
+///
+/// Spans started in Python are then children of the current Rust span, which is useful before
+/// calling back into Python, e.g. to run a hook or plugin. The context is detached from Python
+/// when the returned guard is dropped.
+///
+/// The context is injected with the propagator set with [`set_propagator`] and extracted with
+/// `opentelemetry.propagate.extract`, so, as when propagating from Python to Rust, the Rust and
+/// Python propagators should understand the same formats.
+///
+/// The current Rust context includes the current `tracing` span as long as the
+/// `tracing-opentelemetry` layer activates contexts, which it does by default.
+///
+/// # Examples
+///
+/// ```rust
+/// use pyo3::prelude::*;
+/// use pyo3_opentelemetry::attach_otel_context_to_python;
+///
+/// #[tracing::instrument(skip_all)]
+/// fn call_hook(py: Python<'_>, hook: &Bound<'_, PyAny>) -> PyResult<()> {
+///     let _guard = attach_otel_context_to_python(py)?;
+///     hook.call0()?;
+///     Ok(())
+/// }
+/// ```
+///
+/// # Errors
+///
+/// Any Python error that occurs while trying to attach the context in Python will be returned;
+/// this includes import errors when importing `opentelemetry.context` and `opentelemetry.propagate`,
+/// other than the `opentelemetry` package not being installed, in which case nothing is attached.
+pub fn attach_otel_context_to_python(py: Python<'_>) -> PyResult<PythonContextGuard> {
+    let propagator = current_propagator();
+    let mut carrier = HashMap::<String, String>::new();
+    propagator.inject_context(&Context::current(), &mut carrier);
+
+    let Some(api) = PythonOtelApi::get(py)? else {
+        return Ok(PythonContextGuard {
+            token: None,
+            _not_send: PhantomData,
+        });
+    };
+    let context = api.extract.bind(py).call1((carrier.into_py_dict(py)?,))?;
+    let token = api.attach.bind(py).call1((context,))?;
+
+    Ok(PythonContextGuard {
+        token: Some(token.unbind()),
+        _not_send: PhantomData,
+    })
+}
+
+/// A guard that detaches an `OpenTelemetry` context from Python when dropped,
+/// returned by [`attach_otel_context_to_python`].
+///
+/// Like Python's own context tokens, the guard must be dropped on the thread it was created on,
+/// and guards should be dropped in the reverse order that they were created.
+#[derive(Debug)]
+#[must_use = "the context is detached from Python when the guard is dropped"]
+pub struct PythonContextGuard {
+    /// The token returned by `opentelemetry.context.attach`, or `None` if nothing was attached.
+    token: Option<Py<PyAny>>,
+    /// Python context tokens are only valid on the thread that created them.
+    _not_send: PhantomData<*const ()>,
+}
+
+impl Drop for PythonContextGuard {
+    fn drop(&mut self) {
+        let Some(token) = self.token.take() else {
+            return;
+        };
+        Python::attach(|py| {
+            // The API must be available, since it was used to attach the context.
+            let detached = PythonOtelApi::get(py).and_then(|api| {
+                api.map(|api| api.detach.bind(py).call1((token,)))
+                    .transpose()
+            });
+            if let Err(error) = detached {
+                error.write_unraisable(py, None);
+            }
+        });
+    }
 }
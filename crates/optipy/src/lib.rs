@@ -15,26 +15,70 @@
 //! A procedural macro that removes [`pyo3`][] related macros.
 #![doc = include_str!("../README.md")]
 
+use std::collections::HashMap;
+
 use proc_macro::TokenStream;
 use quote::quote;
 use syn::{
-    Attribute, Field, ImplItemConst, ImplItemFn, Item, ItemEnum, ItemFn, ItemImpl, ItemMod,
-    ItemStruct, PatType, Receiver, Variant, parse_macro_input,
+    Attribute, ExprClosure, Field, Ident, ImplItem, ImplItemConst, ImplItemFn, ImplItemType, Item,
+    ItemConst, ItemEnum, ItemFn, ItemImpl, ItemMod, ItemStatic, ItemStruct, ItemTrait, ItemType,
+    ItemUnion, Meta, PatIdent, PatType, Path, Receiver, Token, TraitItem, TraitItemConst,
+    TraitItemFn, TraitItemType, UseTree, Variant, bracketed,
+    meta::ParseNestedMeta,
+    parse_macro_input, parse_quote,
+    punctuated::Punctuated,
+    visit::{self, Visit},
     visit_mut::{self, VisitMut},
 };
 
+mod python;
+
 /// Strips `PyO3` attributes from the annotated item and its children.
 ///
 /// Pass `only_stubs` to strip only `#[gen_stub]` attributes, leaving the rest
 /// of the `PyO3` attributes in place.
+///
+/// Pass `also = [...]` to strip additional attributes, such as `pypropagate` or `gen_stub_pyclass`,
+/// and `keep = [...]` to leave attributes in place that would otherwise be stripped.
+/// Attributes are matched by their final path segments,
+/// so `gen_stub_pymethods` matches `#[pyo3_stub_gen::derive::gen_stub_pymethods]`, too.
+///
+/// Attributes wrapped in `cfg_attr` are stripped from it as well,
+/// and the `cfg_attr` itself is removed if nothing is left in it.
+///
+/// Pass `items` to also remove whole items that can't compile without `PyO3`:
+/// those marked [`#[py_only]`](macro@py_only), `use` declarations that import from `pyo3`,
+/// and items whose signatures reference `PyO3` types such as `Python`, `Bound`, or `PyResult`.
+/// Only signatures are checked, not function bodies.
+/// Within a module, a type belongs to `PyO3` if its path starts with a `PyO3` crate
+/// or with a name the module imports from one. Where the imports aren't visible,
+/// such as on an `impl` block or after a glob import, the common `PyO3` type names count, too,
+/// unless their path starts with another crate, e.g. `std::ops::Bound`.
+///
+/// Pass `strict` to report an error for each attribute that looks like it belongs to `PyO3`
+/// but isn't stripped, such as a misspelling or an attribute added in a newer version of `PyO3`.
+/// Attributes look like they belong to `PyO3` if their paths start with a `PyO3` crate,
+/// or if their names start with `py` or `gen_stub`; list them in `keep` to allow them.
 #[proc_macro_attribute]
 pub fn strip_pyo3(attr: TokenStream, item: TokenStream) -> TokenStream {
-    let mut stripper = StripPyO3 { only_stubs: false };
+    let mut stripper = StripPyO3::default();
 
     let opt_parser = syn::meta::parser(|meta| {
         if meta.path.is_ident("only_stubs") {
             stripper.only_stubs = true;
             Ok(())
+        } else if meta.path.is_ident("items") {
+            stripper.items = true;
+            Ok(())
+        } else if meta.path.is_ident("strict") {
+            stripper.strict = true;
+            Ok(())
+        } else if meta.path.is_ident("also") {
+            stripper.also.extend(parse_path_list(&meta)?);
+            Ok(())
+        } else if meta.path.is_ident("keep") {
+            stripper.keep.extend(parse_path_list(&meta)?);
+            Ok(())
         } else {
             Err(meta.error("unsupported strip_pyo3 property"))
         }
@@ -42,12 +86,131 @@ pub fn strip_pyo3(attr: TokenStream, item: TokenStream) -> TokenStream {
     parse_macro_input!(attr with opt_parser);
 
     let mut input = parse_macro_input!(item as Item);
+    if stripper.items && is_py_only_item(&input, &Imports::default()) {
+        return TokenStream::new();
+    }
     stripper.visit_item_mut(&mut input);
-    quote!(#input).into()
+    let errors = stripper.errors.iter().map(syn::Error::to_compile_error);
+    quote!(#(#errors)* #input).into()
+}
+
+/// Marks an item as only needed for Python bindings.
+///
+/// This leaves the item unchanged, but [`strip_pyo3(items)`](macro@strip_pyo3) removes it entirely,
+/// so it can be used for methods like `__repr__` that don't otherwise reference `PyO3` types.
+#[proc_macro_attribute]
+pub fn py_only(attr: TokenStream, item: TokenStream) -> TokenStream {
+    let no_args = syn::meta::parser(|meta| Err(meta.error("py_only doesn't take any arguments")));
+    parse_macro_input!(attr with no_args);
+    item
+}
+
+/// Applies `PyO3`, `pyo3_stub_gen` and [`strip_pyo3`](macro@strip_pyo3) attributes
+/// to a struct, enum, `impl` block or function, depending on which features are enabled.
+///
+/// - `feature = "..."` names the feature that enables the Python bindings.
+///   Without it, everything `PyO3`-related is stripped.
+///   If it's omitted, the bindings are always enabled.
+/// - `stubs = "..."` names the feature that enables stub generation, which should imply `feature`.
+///   Without it, only the `pyo3_stub_gen` attributes are stripped.
+///   If it's omitted, stubs aren't generated.
+/// - `items`, `strict`, `also = [...]` and `keep = [...]` are passed on to `strip_pyo3`
+///   for when the Python bindings are disabled.
+///
+/// The matching `PyO3` macro (`pyclass`, `pymethods` or `pyfunction`) is added
+/// unless the item already has it, which is useful to give it arguments.
+/// In that case, `python` must come first, so the `pyo3_stub_gen` macro is applied before it.
+/// The generated attributes refer to `::pyo3` and `::pyo3_stub_gen`,
+/// so the crate must depend on them directly.
+///
+/// ```rust,ignore
+/// #[optipy::python(feature = "python", stubs = "stubs")]
+/// #[pyclass(module = "yak_shaving")]
+/// struct Yak {
+///     #[pyo3(get)]
+///     is_shaved: bool,
+/// }
+/// ```
+#[proc_macro_attribute]
+pub fn python(attr: TokenStream, item: TokenStream) -> TokenStream {
+    let mut args = python::PythonArgs::default();
+    let opt_parser = syn::meta::parser(|meta| args.parse(&meta));
+    parse_macro_input!(attr with opt_parser);
+
+    let input = parse_macro_input!(item as Item);
+    python::expand(&args, &input)
+        .unwrap_or_else(syn::Error::into_compile_error)
+        .into()
+}
+
+/// Parse the value of a `name = [path, ...]` property.
+fn parse_path_list(meta: &ParseNestedMeta) -> syn::Result<Punctuated<Path, Token![,]>> {
+    let value = meta.value()?;
+    let content;
+    bracketed!(content in value);
+    Punctuated::parse_terminated(&content)
 }
 
+/// Attributes processed by `PyO3`.
+const PYO3_ATTRS: &[&str] = &[
+    "pyo3",
+    "new",
+    "getter",
+    "setter",
+    "pyclass",
+    "pymethods",
+    "pyfunction",
+    "pymodule",
+    "staticmethod",
+    "classmethod",
+    "classattr",
+    "args",
+];
+
+/// Attributes processed by `pyo3_stub_gen`.
+const STUB_ATTRS: &[&str] = &["gen_stub"];
+
+/// Crates whose items can't be referenced without `PyO3`.
+const PYO3_CRATES: &[&str] = &["pyo3", "pyo3_stub_gen", "pyo3_async_runtimes"];
+
+/// Commonly used `PyO3` types, which are usually imported rather than written with a path.
+const PYO3_TYPES: &[&str] = &[
+    "Borrowed",
+    "Bound",
+    "CompareOp",
+    "FromPyObject",
+    "IntoPyObject",
+    "Py",
+    "PyAny",
+    "PyClassInitializer",
+    "PyErr",
+    "PyModule",
+    "PyObject",
+    "PyRef",
+    "PyRefMut",
+    "PyResult",
+    "PyType",
+    "Python",
+];
+
+/// Path roots that never refer to a `PyO3` item.
+const NON_PYO3_ROOTS: &[&str] = &["std", "core", "alloc", "crate", "self", "super", "Self"];
+
+#[derive(Default)]
 struct StripPyO3 {
     only_stubs: bool,
+    /// Whether to remove whole items that can't compile without `PyO3`.
+    items: bool,
+    /// Additional attributes to strip.
+    also: Vec<Path>,
+    /// Attributes to keep, even if they would otherwise be stripped.
+    keep: Vec<Path>,
+    /// Whether to report kept attributes that look like they belong to `PyO3`.
+    strict: bool,
+    /// Errors for the attributes reported in strict mode.
+    errors: Vec<syn::Error>,
+    /// The imports of the module being visited.
+    imports: Imports,
 }
 
 /// Visit a type by filtering its attributes, then delegating to its default implementation.
@@ -62,47 +225,407 @@ macro_rules! filter_visitor {
 
 /// Implement visitors that strip PyO3 attributes from relevant types.
 impl VisitMut for StripPyO3 {
+    filter_visitor!(visit_expr_closure_mut(ExprClosure));
     filter_visitor!(visit_field_mut(Field));
     filter_visitor!(visit_impl_item_const_mut(ImplItemConst));
     filter_visitor!(visit_impl_item_fn_mut(ImplItemFn));
+    filter_visitor!(visit_impl_item_type_mut(ImplItemType));
+    filter_visitor!(visit_item_const_mut(ItemConst));
     filter_visitor!(visit_item_enum_mut(ItemEnum));
     filter_visitor!(visit_item_fn_mut(ItemFn));
-    filter_visitor!(visit_item_impl_mut(ItemImpl));
-    filter_visitor!(visit_item_mod_mut(ItemMod));
+    filter_visitor!(visit_item_static_mut(ItemStatic));
     filter_visitor!(visit_item_struct_mut(ItemStruct));
+    filter_visitor!(visit_item_type_mut(ItemType));
+    filter_visitor!(visit_item_union_mut(ItemUnion));
+    filter_visitor!(visit_pat_ident_mut(PatIdent));
     filter_visitor!(visit_pat_type_mut(PatType));
     filter_visitor!(visit_receiver_mut(Receiver));
+    filter_visitor!(visit_trait_item_const_mut(TraitItemConst));
+    filter_visitor!(visit_trait_item_fn_mut(TraitItemFn));
+    filter_visitor!(visit_trait_item_type_mut(TraitItemType));
     filter_visitor!(visit_variant_mut(Variant));
+
+    fn visit_item_impl_mut(&mut self, i: &mut ItemImpl) {
+        self.filter_pyo3_attrs(&mut i.attrs);
+        if self.items {
+            i.items
+                .retain(|item| !is_py_only_impl_item(item, &self.imports));
+        }
+        visit_mut::visit_item_impl_mut(self, i);
+    }
+
+    fn visit_item_mod_mut(&mut self, i: &mut ItemMod) {
+        self.filter_pyo3_attrs(&mut i.attrs);
+        let Some((_, items)) = &mut i.content else {
+            return;
+        };
+        // A module only sees its own imports, not those of its parent.
+        let outer = std::mem::replace(&mut self.imports, Imports::of_module(items));
+        if self.items {
+            items.retain(|item| !is_py_only_item(item, &self.imports));
+        }
+        visit_mut::visit_item_mod_mut(self, i);
+        self.imports = outer;
+    }
+
+    fn visit_item_trait_mut(&mut self, i: &mut ItemTrait) {
+        self.filter_pyo3_attrs(&mut i.attrs);
+        if self.items {
+            i.items
+                .retain(|item| !is_py_only_trait_item(item, &self.imports));
+        }
+        visit_mut::visit_item_trait_mut(self, i);
+    }
 }
 
 impl StripPyO3 {
     /// Filter out `PyO3` attributes.
-    fn filter_pyo3_attrs(&self, attrs: &mut Vec<Attribute>) {
+    fn filter_pyo3_attrs(&mut self, attrs: &mut Vec<Attribute>) {
+        attrs.retain_mut(|attr| self.filter_meta(&mut attr.meta));
+        if self.strict {
+            for attr in attrs {
+                self.check_kept(&attr.meta);
+            }
+        }
+    }
+
+    /// In strict mode, report a kept attribute, or those kept in a `cfg_attr`,
+    /// if it looks like it belongs to `PyO3` but wasn't stripped.
+    fn check_kept(&mut self, meta: &Meta) {
+        if let Meta::List(list) = meta
+            && list.path.is_ident("cfg_attr")
+        {
+            if let Ok(args) = list.parse_args_with(Punctuated::<Meta, Token![,]>::parse_terminated)
+            {
+                args.iter().skip(1).for_each(|attr| self.check_kept(attr));
+            }
+            return;
+        }
+
+        let path = meta.path();
+        if self.looks_like_pyo3(path) && !self.keep.iter().any(|keep| ends_with(path, keep)) {
+            let name = quote!(#path).to_string().replace(' ', "");
+            self.errors.push(syn::Error::new_spanned(
+                meta,
+                format!(
+                    "strip_pyo3 doesn't know `#[{name}]`; \
+                     list it in `also = [...]` to strip it, or in `keep = [...]` to keep it"
+                ),
+            ));
+        }
+    }
+
+    /// Whether an attribute that wasn't stripped looks like it belongs to `PyO3`,
+    /// or just to `pyo3_stub_gen` in `only_stubs` mode.
+    fn looks_like_pyo3(&self, path: &Path) -> bool {
+        let (Some(first), Some(last)) = (path.segments.first(), path.segments.last()) else {
+            return false;
+        };
+        let last = last.ident.to_string();
+
         if self.only_stubs {
-            attrs.retain(|attr| !matches!(attr.path().get_ident(), Some(id) if id == "gen_stub"));
+            first.ident == "pyo3_stub_gen" || last.starts_with("gen_stub")
         } else {
-            attrs.retain(|attr| match attr.path().get_ident() {
-                None => true,
-                Some(id) => {
-                    !(id == "pyo3"
-                        || id == "new"
-                        || id == "getter"
-                        || id == "setter"
-                        || id == "pyclass"
-                        || id == "pymethods"
-                        || id == "pyfunction"
-                        || id == "pymodule"
-                        || id == "staticmethod"
-                        || id == "classmethod"
-                        || id == "classattr"
-                        || id == "args"
-                        || id == "gen_stub")
+            is_pyo3_crate(&first.ident)
+                || (last.starts_with("py") && last != "py_only")
+                || last.starts_with("gen_stub")
+        }
+    }
+
+    /// Filter `PyO3` attributes out of a `cfg_attr`, or check whether to keep any other attribute.
+    ///
+    /// Returns `false` if the whole attribute should be removed.
+    fn filter_meta(&self, meta: &mut Meta) -> bool {
+        let Meta::List(list) = meta else {
+            return !self.is_stripped(meta.path());
+        };
+        if !list.path.is_ident("cfg_attr") {
+            return !self.is_stripped(&list.path);
+        }
+
+        // Leave anything we can't parse for the compiler to report.
+        let Ok(args) = list.parse_args_with(Punctuated::<Meta, Token![,]>::parse_terminated) else {
+            return true;
+        };
+        let mut args = args.into_iter();
+        let Some(predicate) = args.next() else {
+            return true;
+        };
+
+        let attrs: Vec<_> = args
+            .filter_map(|mut attr| self.filter_meta(&mut attr).then_some(attr))
+            .collect();
+        if attrs.is_empty() {
+            return false;
+        }
+
+        *meta = parse_quote!(cfg_attr(#predicate, #(#attrs),*));
+        true
+    }
+
+    /// Whether an attribute with this path should be stripped.
+    fn is_stripped(&self, path: &Path) -> bool {
+        if self.keep.iter().any(|keep| ends_with(path, keep)) {
+            return false;
+        }
+
+        let builtin = path.segments.last().is_some_and(|last| {
+            STUB_ATTRS.iter().any(|name| last.ident == name)
+                || (!self.only_stubs && PYO3_ATTRS.iter().any(|name| last.ident == name))
+        });
+
+        builtin || self.also.iter().any(|also| ends_with(path, also))
+    }
+}
+
+/// Whether an item is marked `#[py_only]`, imports from `PyO3`, or has a signature using `PyO3` types.
+fn is_py_only_item(item: &Item, imports: &Imports) -> bool {
+    let mut finder = FindPyO3::new(imports);
+    let attrs = match item {
+        Item::Const(i) => {
+            finder.visit_type(&i.ty);
+            &i.attrs
+        }
+        Item::Enum(i) => {
+            finder.visit_item_enum(i);
+            &i.attrs
+        }
+        Item::Fn(i) => {
+            finder.visit_signature(&i.sig);
+            &i.attrs
+        }
+        Item::Impl(i) => {
+            finder.visit_generics(&i.generics);
+            if let Some((_, path, _)) = &i.trait_ {
+                finder.visit_path(path);
+            }
+            finder.visit_type(&i.self_ty);
+            &i.attrs
+        }
+        Item::Static(i) => {
+            finder.visit_type(&i.ty);
+            &i.attrs
+        }
+        Item::Struct(i) => {
+            finder.visit_item_struct(i);
+            &i.attrs
+        }
+        Item::Trait(i) => {
+            finder.visit_generics(&i.generics);
+            for bound in &i.supertraits {
+                finder.visit_type_param_bound(bound);
+            }
+            &i.attrs
+        }
+        Item::Type(i) => {
+            finder.visit_item_type(i);
+            &i.attrs
+        }
+        Item::Union(i) => {
+            finder.visit_item_union(i);
+            &i.attrs
+        }
+        Item::Use(i) => {
+            finder.found = is_pyo3_use(&i.tree);
+            &i.attrs
+        }
+        Item::ExternCrate(i) => {
+            finder.found = is_pyo3_crate(&i.ident);
+            &i.attrs
+        }
+        Item::ForeignMod(i) => &i.attrs,
+        Item::Macro(i) => &i.attrs,
+        Item::Mod(i) => &i.attrs,
+        Item::TraitAlias(i) => &i.attrs,
+        _ => return false,
+    };
+    finder.found || has_py_only(attrs)
+}
+
+/// Whether an `impl` item is marked `#[py_only]` or has a signature using `PyO3` types.
+fn is_py_only_impl_item(item: &ImplItem, imports: &Imports) -> bool {
+    let mut finder = FindPyO3::new(imports);
+    let attrs = match item {
+        ImplItem::Const(i) => {
+            finder.visit_type(&i.ty);
+            &i.attrs
+        }
+        ImplItem::Fn(i) => {
+            finder.visit_signature(&i.sig);
+            &i.attrs
+        }
+        ImplItem::Type(i) => {
+            finder.visit_type(&i.ty);
+            &i.attrs
+        }
+        ImplItem::Macro(i) => &i.attrs,
+        _ => return false,
+    };
+    finder.found || has_py_only(attrs)
+}
+
+/// Whether a trait item is marked `#[py_only]` or has a signature using `PyO3` types.
+fn is_py_only_trait_item(item: &TraitItem, imports: &Imports) -> bool {
+    let mut finder = FindPyO3::new(imports);
+    let attrs = match item {
+        TraitItem::Const(i) => {
+            finder.visit_type(&i.ty);
+            &i.attrs
+        }
+        TraitItem::Fn(i) => {
+            finder.visit_signature(&i.sig);
+            &i.attrs
+        }
+        TraitItem::Type(i) => {
+            finder.visit_trait_item_type(i);
+            &i.attrs
+        }
+        TraitItem::Macro(i) => &i.attrs,
+        _ => return false,
+    };
+    finder.found || has_py_only(attrs)
+}
+
+/// Whether the attributes include `#[py_only]`.
+fn has_py_only(attrs: &[Attribute]) -> bool {
+    attrs.iter().any(|attr| {
+        attr.path()
+            .segments
+            .last()
+            .is_some_and(|last| last.ident == "py_only")
+    })
+}
+
+/// Whether a `use` tree imports from a `PyO3` crate.
+fn is_pyo3_use(tree: &UseTree) -> bool {
+    match tree {
+        UseTree::Path(path) => is_pyo3_crate(&path.ident),
+        UseTree::Name(name) => is_pyo3_crate(&name.ident),
+        UseTree::Rename(rename) => is_pyo3_crate(&rename.ident),
+        UseTree::Group(group) => group.items.iter().any(is_pyo3_use),
+        UseTree::Glob(_) => false,
+    }
+}
+
+/// Whether an identifier names a `PyO3` crate.
+fn is_pyo3_crate(ident: &Ident) -> bool {
+    PYO3_CRATES.iter().any(|name| ident == name)
+}
+
+/// The names brought into scope by the `use` declarations of a module.
+#[derive(Default)]
+struct Imports {
+    /// Whether the imports are known, i.e. the item being checked is within a module.
+    visible: bool,
+    /// Whether a glob import could bring any other name into scope.
+    glob: bool,
+    /// Names imported explicitly, and whether each is imported from a `PyO3` crate.
+    names: HashMap<String, bool>,
+}
+
+impl Imports {
+    /// Collect the imports of a module with these items.
+    fn of_module(items: &[Item]) -> Self {
+        let mut imports = Self {
+            visible: true,
+            ..Self::default()
+        };
+        for item in items {
+            if let Item::Use(item) = item {
+                imports.add(&item.tree, None, None);
+            }
+        }
+        imports
+    }
+
+    /// Add the names imported by a `use` tree, within the path segment `parent`
+    /// of a path that's from a `PyO3` crate if `from_pyo3` is `Some(true)`.
+    fn add(&mut self, tree: &UseTree, parent: Option<&Ident>, from_pyo3: Option<bool>) {
+        match tree {
+            UseTree::Path(path) => {
+                let from_pyo3 = from_pyo3.unwrap_or_else(|| is_pyo3_crate(&path.ident));
+                self.add(&path.tree, Some(&path.ident), Some(from_pyo3));
+            }
+            UseTree::Name(name) => {
+                // `use pyo3::types::{self}` imports `types`.
+                let ident = if name.ident == "self" {
+                    parent
+                } else {
+                    Some(&name.ident)
+                };
+                if let Some(ident) = ident {
+                    let from_pyo3 = from_pyo3.unwrap_or_else(|| is_pyo3_crate(&name.ident));
+                    self.names.insert(ident.to_string(), from_pyo3);
                 }
-            });
+            }
+            UseTree::Rename(rename) => {
+                let from_pyo3 = from_pyo3.unwrap_or_else(|| is_pyo3_crate(&rename.ident));
+                self.names.insert(rename.rename.to_string(), from_pyo3);
+            }
+            UseTree::Glob(_) => self.glob = true,
+            UseTree::Group(group) => {
+                for item in &group.items {
+                    self.add(item, parent, from_pyo3);
+                }
+            }
+        }
+    }
+
+    /// Whether a path refers to a `PyO3` crate or type.
+    fn is_pyo3(&self, path: &Path) -> bool {
+        let (Some(first), Some(last)) = (path.segments.first(), path.segments.last()) else {
+            return false;
+        };
+        if is_pyo3_crate(&first.ident) {
+            return true;
+        }
+        if path.leading_colon.is_some() || NON_PYO3_ROOTS.iter().any(|root| first.ident == root) {
+            return false;
+        }
+        if let Some(&from_pyo3) = self.names.get(&first.ident.to_string()) {
+            return from_pyo3;
+        }
+        (!self.visible || self.glob) && PYO3_TYPES.iter().any(|name| last.ident == name)
+    }
+}
+
+/// Finds references to `PyO3` crates and types, ignoring attributes.
+struct FindPyO3<'a> {
+    /// The imports in scope, used to resolve names without a path.
+    imports: &'a Imports,
+    found: bool,
+}
+
+impl<'a> FindPyO3<'a> {
+    const fn new(imports: &'a Imports) -> Self {
+        Self {
+            imports,
+            found: false,
         }
     }
 }
 
+impl Visit<'_> for FindPyO3<'_> {
+    fn visit_attribute(&mut self, _: &Attribute) {}
+
+    fn visit_path(&mut self, path: &Path) {
+        self.found |= self.imports.is_pyo3(path);
+        visit::visit_path(self, path);
+    }
+}
+
+/// Whether the segments of `path` end with those of `suffix`,
+/// e.g. `pyo3::pyclass` ends with `pyclass`.
+fn ends_with(path: &Path, suffix: &Path) -> bool {
+    path.segments.len() >= suffix.segments.len()
+        && path
+            .segments
+            .iter()
+            .rev()
+            .zip(suffix.segments.iter().rev())
+            .all(|(segment, suffix)| segment.ident == suffix.ident)
+}
+
 #[cfg(test)]
 mod tests {
     use super::StripPyO3;
@@ -185,7 +708,7 @@ mod tests {
             }
         );
 
-        StripPyO3 { only_stubs: false }.visit_item_mut(&mut input);
+        StripPyO3::default().visit_item_mut(&mut input);
         let result = format!("{}", quote!(#input));
         assert_eq!(format!("{result}"), expected);
     }
@@ -213,7 +736,11 @@ mod tests {
             }
         );
 
-        StripPyO3 { only_stubs: true }.visit_item_mut(&mut input);
+        StripPyO3 {
+            only_stubs: true,
+            ..StripPyO3::default()
+        }
+        .visit_item_mut(&mut input);
         let result = format!("{}", quote!(#input));
         assert_eq!(format!("{result}"), expected);
     }
@@ -240,8 +767,386 @@ mod tests {
             }
         );
 
-        StripPyO3 { only_stubs: false }.visit_item_mut(&mut input);
+        StripPyO3::default().visit_item_mut(&mut input);
+        let result = format!("{}", quote!(#input));
+        assert_eq!(format!("{result}"), expected);
+    }
+
+    #[test]
+    fn test_strip_also_and_keep() {
+        let mut input: Item = parse_quote! {
+            #[pyo3_stub_gen::derive::gen_stub_pymethods]
+            #[pyo3::pymethods]
+            impl S {
+                #[new]
+                #[pyo3_opentelemetry::pypropagate]
+                fn new() -> Self {
+                    Self
+                }
+
+                #[gen_stub(skip)]
+                #[custom::attr]
+                #[attr]
+                #[other]
+                fn foo(&self) {}
+            }
+        };
+
+        let expected = format!(
+            "{}",
+            quote! {
+                impl S {
+                    #[new]
+                    fn new() -> Self {
+                        Self
+                    }
+
+                    #[attr]
+                    #[other]
+                    fn foo(&self) {}
+                }
+            }
+        );
+
+        StripPyO3 {
+            also: vec![
+                parse_quote!(pypropagate),
+                parse_quote!(gen_stub_pymethods),
+                parse_quote!(custom::attr),
+            ],
+            keep: vec![parse_quote!(new)],
+            ..StripPyO3::default()
+        }
+        .visit_item_mut(&mut input);
+        let result = format!("{}", quote!(#input));
+        assert_eq!(format!("{result}"), expected);
+    }
+
+    #[test]
+    fn test_strip_cfg_attr() {
+        let mut input: Item = parse_quote! {
+            #[cfg_attr(feature = "python", pyo3::pyclass(frozen), derive(Clone))]
+            #[cfg_attr(feature = "stubs", gen_stub_pyclass)]
+            struct S {
+                #[cfg_attr(feature = "python", pyo3(get))]
+                field: i32,
+                #[cfg_attr(feature = "python", cfg_attr(feature = "stubs", gen_stub(skip)))]
+                other: i32,
+            }
+        };
+
+        let expected = format!(
+            "{}",
+            quote! {
+                #[cfg_attr(feature = "python", derive(Clone))]
+                struct S {
+                    field: i32,
+                    other: i32,
+                }
+            }
+        );
+
+        StripPyO3 {
+            also: vec![parse_quote!(gen_stub_pyclass)],
+            ..StripPyO3::default()
+        }
+        .visit_item_mut(&mut input);
+        let result = format!("{}", quote!(#input));
+        assert_eq!(format!("{result}"), expected);
+    }
+
+    #[test]
+    fn test_strip_other_items() {
+        let mut input: Item = parse_quote! {
+            mod module {
+                #[pyo3::pyclass]
+                trait T {
+                    #[classattr]
+                    const C: i32;
+                    #[pyo3(name = "Assoc")]
+                    type A;
+                    #[getter]
+                    fn f(&self, #[pyo3(from_py_with = foo)] x: i32);
+                }
+
+                #[pyo3(name = "Alias")]
+                type Alias = i32;
+
+                #[classattr]
+                const C: i32 = 1;
+
+                #[classattr]
+                static S: i32 = 1;
+
+                impl T for S {
+                    #[pyo3(name = "Assoc")]
+                    type A = i32;
+                    fn f(&self, x: i32) {
+                        let _ = #[pyo3] |#[pyo3] y, #[pyo3] z: i32| y + z;
+                    }
+                }
+            }
+        };
+
+        let expected = format!(
+            "{}",
+            quote! {
+                mod module {
+                    trait T {
+                        const C: i32;
+                        type A;
+                        fn f(&self, x: i32);
+                    }
+
+                    type Alias = i32;
+
+                    const C: i32 = 1;
+
+                    static S: i32 = 1;
+
+                    impl T for S {
+                        type A = i32;
+                        fn f(&self, x: i32) {
+                            let _ = |y, z: i32| y + z;
+                        }
+                    }
+                }
+            }
+        );
+
+        StripPyO3::default().visit_item_mut(&mut input);
+        let result = format!("{}", quote!(#input));
+        assert_eq!(format!("{result}"), expected);
+    }
+
+    #[test]
+    fn test_strip_items() {
+        let mut input: Item = parse_quote! {
+            mod module {
+                use pyo3::prelude::*;
+                use std::fmt;
+
+                #[pyclass]
+                struct S {
+                    field: i32,
+                }
+
+                struct Wrapper(Py<PyAny>);
+
+                #[pymethods]
+                impl S {
+                    #[new]
+                    fn new(field: i32) -> Self {
+                        Self { field }
+                    }
+
+                    #[py_only]
+                    fn __repr__(&self) -> String {
+                        format!("S({})", self.field)
+                    }
+
+                    fn __richcmp__(&self, other: &Self, op: CompareOp) -> bool {
+                        op.matches(self.field.cmp(&other.field))
+                    }
+
+                    #[staticmethod]
+                    fn parse(py: Python<'_>, value: &Bound<'_, PyAny>) -> PyResult<Self> {
+                        value.extract().map(Self::new)
+                    }
+
+                    fn double(&self) -> i32 {
+                        self.field * 2
+                    }
+                }
+
+                impl<'py> IntoPyObject<'py> for Wrapper {}
+
+                #[pyfunction]
+                fn make(field: i32) -> pyo3::PyResult<S> {
+                    Ok(S::new(field))
+                }
+            }
+        };
+
+        let expected = format!(
+            "{}",
+            quote! {
+                mod module {
+                    use std::fmt;
+
+                    struct S {
+                        field: i32,
+                    }
+
+                    impl S {
+                        fn new(field: i32) -> Self {
+                            Self { field }
+                        }
+
+                        fn double(&self) -> i32 {
+                            self.field * 2
+                        }
+                    }
+                }
+            }
+        );
+
+        StripPyO3 {
+            items: true,
+            ..StripPyO3::default()
+        }
+        .visit_item_mut(&mut input);
+        let result = format!("{}", quote!(#input));
+        assert_eq!(format!("{result}"), expected);
+    }
+
+    #[test]
+    fn test_strip_items_ignores_other_crates() {
+        let mut input: Item = parse_quote! {
+            mod module {
+                use pyo3::prelude::*;
+                use pyo3::types::{self, PyDict as Dict};
+                use std::ops::Bound;
+
+                fn range(start: Bound<i32>) -> (Bound<i32>, Bound<i32>) {
+                    (start, Bound::Unbounded)
+                }
+
+                fn py_range(start: std::ops::Bound<i32>) -> crate::Py {
+                    todo!()
+                }
+
+                fn dict(dict: &Dict) {}
+
+                fn any(any: &types::PyAny) {}
+
+                mod inner {
+                    fn range(start: Bound<i32>) {}
+                }
+            }
+        };
+
+        let expected = format!(
+            "{}",
+            quote! {
+                mod module {
+                    use std::ops::Bound;
+
+                    fn range(start: Bound<i32>) -> (Bound<i32>, Bound<i32>) {
+                        (start, Bound::Unbounded)
+                    }
+
+                    fn py_range(start: std::ops::Bound<i32>) -> crate::Py {
+                        todo!()
+                    }
+
+                    mod inner {
+                        fn range(start: Bound<i32>) {}
+                    }
+                }
+            }
+        );
+
+        StripPyO3 {
+            items: true,
+            ..StripPyO3::default()
+        }
+        .visit_item_mut(&mut input);
+        let result = format!("{}", quote!(#input));
+        assert_eq!(format!("{result}"), expected);
+    }
+
+    #[test]
+    fn test_strip_items_without_visible_imports() {
+        let mut input: Item = parse_quote! {
+            impl S {
+                fn range(&self, start: std::ops::Bound<i32>) {}
+
+                fn parse(value: &Bound<'_, PyAny>) -> PyResult<Self> {
+                    todo!()
+                }
+
+                fn any(value: &types::PyAny) {}
+            }
+        };
+
+        let expected = format!(
+            "{}",
+            quote! {
+                impl S {
+                    fn range(&self, start: std::ops::Bound<i32>) {}
+                }
+            }
+        );
+
+        StripPyO3 {
+            items: true,
+            ..StripPyO3::default()
+        }
+        .visit_item_mut(&mut input);
         let result = format!("{}", quote!(#input));
         assert_eq!(format!("{result}"), expected);
     }
+
+    #[test]
+    fn test_strict_reports_unknown_attributes() {
+        let mut input: Item = parse_quote! {
+            #[pyclass]
+            #[pyclass_init]
+            #[derive(Debug)]
+            struct S {
+                #[pyo3get]
+                #[cfg_attr(feature = "python", pyo3(get), pyo3::new_thing)]
+                field: i32,
+                #[pyproject]
+                #[py_only]
+                other: i32,
+            }
+        };
+
+        let mut stripper = StripPyO3 {
+            strict: true,
+            keep: vec![parse_quote!(pyproject)],
+            ..StripPyO3::default()
+        };
+        stripper.visit_item_mut(&mut input);
+
+        let reported: Vec<_> = stripper
+            .errors
+            .iter()
+            .map(|error| {
+                let message = error.to_string();
+                message[message.find('`').unwrap()..=message.find(']').unwrap() + 1].to_string()
+            })
+            .collect();
+        assert_eq!(
+            reported,
+            ["`#[pyclass_init]`", "`#[pyo3get]`", "`#[pyo3::new_thing]`"]
+        );
+    }
+
+    #[test]
+    fn test_strict_only_stubs() {
+        let mut input: Item = parse_quote! {
+            #[pymethods]
+            impl S {
+                #[gen_stub_unknown]
+                #[pyo3(signature = ())]
+                fn foo(&self) {}
+            }
+        };
+
+        let mut stripper = StripPyO3 {
+            only_stubs: true,
+            strict: true,
+            ..StripPyO3::default()
+        };
+        stripper.visit_item_mut(&mut input);
+        assert_eq!(stripper.errors.len(), 1);
+        assert!(
+            stripper.errors[0].to_string().contains("gen_stub_unknown"),
+            "{}",
+            stripper.errors[0]
+        );
+    }
 }
@@ -0,0 +1,254 @@
+// Copyright 2025 Rigetti Computing
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+// http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! Expansion of the `#[optipy::python]` attribute.
+
+use proc_macro2::TokenStream;
+use quote::quote;
+use syn::{Attribute, Fields, Item, LitStr, meta::ParseNestedMeta, spanned::Spanned};
+
+use crate::parse_path_list;
+
+/// The options given to `#[optipy::python(...)]`.
+#[derive(Default)]
+pub(crate) struct PythonArgs {
+    /// The feature that enables the Python bindings; if `None`, they're always enabled.
+    feature: Option<LitStr>,
+    /// The feature that enables stub generation; if `None`, stubs aren't generated.
+    stubs: Option<LitStr>,
+    /// Options forwarded to `strip_pyo3` when the Python bindings are disabled.
+    strip: Vec<TokenStream>,
+}
+
+impl PythonArgs {
+    /// Parse a single option.
+    pub(crate) fn parse(&mut self, meta: &ParseNestedMeta) -> syn::Result<()> {
+        if meta.path.is_ident("feature") {
+            self.feature = Some(meta.value()?.parse()?);
+        } else if meta.path.is_ident("stubs") {
+            self.stubs = Some(meta.value()?.parse()?);
+        } else if meta.path.is_ident("items") {
+            self.strip.push(quote!(items));
+        } else if meta.path.is_ident("strict") {
+            self.strip.push(quote!(strict));
+        } else if meta.path.is_ident("also") {
+            let paths = parse_path_list(meta)?;
+            self.strip.push(quote!(also = [#paths]));
+        } else if meta.path.is_ident("keep") {
+            let paths = parse_path_list(meta)?;
+            self.strip.push(quote!(keep = [#paths]));
+        } else {
+            return Err(meta.error("unsupported python property"));
+        }
+        Ok(())
+    }
+}
+
+/// The `PyO3` and `pyo3_stub_gen` attribute macros that apply to an item.
+struct Macros {
+    /// The name of the `PyO3` attribute macro.
+    pyo3: &'static str,
+    /// The name of the `pyo3_stub_gen` attribute macro.
+    stubs: &'static str,
+}
+
+/// Choose the macros for the kind of item being annotated.
+fn macros_for(item: &Item) -> syn::Result<Macros> {
+    let (pyo3, stubs) = match item {
+        Item::Struct(_) => ("pyclass", "gen_stub_pyclass"),
+        Item::Enum(item)
+            if item
+                .variants
+                .iter()
+                .all(|variant| matches!(variant.fields, Fields::Unit)) =>
+        {
+            ("pyclass", "gen_stub_pyclass_enum")
+        }
+        Item::Enum(_) => ("pyclass", "gen_stub_pyclass_complex_enum"),
+        Item::Impl(_) => ("pymethods", "gen_stub_pymethods"),
+        Item::Fn(_) => ("pyfunction", "gen_stub_pyfunction"),
+        _ => {
+            return Err(syn::Error::new(
+                item.span(),
+                "python can only be applied to structs, enums, impl blocks and functions",
+            ));
+        }
+    };
+    Ok(Macros { pyo3, stubs })
+}
+
+/// The outer attributes of an item, if it's a kind that `python` supports.
+const fn attrs_of(item: &Item) -> &[Attribute] {
+    match item {
+        Item::Struct(item) => item.attrs.as_slice(),
+        Item::Enum(item) => item.attrs.as_slice(),
+        Item::Impl(item) => item.attrs.as_slice(),
+        Item::Fn(item) => item.attrs.as_slice(),
+        _ => &[],
+    }
+}
+
+/// Expand `#[optipy::python(...)]` into the `cfg_attr` attributes for each feature combination.
+///
+/// Without the Python feature, everything `PyO3`-related is stripped.
+/// With it but without the stubs feature, only the `pyo3_stub_gen` attributes are stripped.
+/// With both, the `pyo3_stub_gen` macro is applied before the `PyO3` macro, as it must be.
+pub(crate) fn expand(args: &PythonArgs, item: &Item) -> syn::Result<TokenStream> {
+    let Macros { pyo3, stubs } = macros_for(item)?;
+    let pyo3_macro = syn::Ident::new(pyo3, item.span());
+    let stubs_macro = syn::Ident::new(stubs, item.span());
+
+    let mut attrs = TokenStream::new();
+
+    if let Some(feature) = &args.feature {
+        let strip = &args.strip;
+        attrs.extend(quote! {
+            #[cfg_attr(not(feature = #feature), ::optipy::strip_pyo3(#(#strip),*))]
+        });
+    }
+
+    if let Some(stubs) = &args.stubs {
+        let without_stubs = args.feature.as_ref().map_or_else(
+            || quote!(not(feature = #stubs)),
+            |feature| quote!(all(feature = #feature, not(feature = #stubs))),
+        );
+        attrs.extend(quote! {
+            #[cfg_attr(#without_stubs, ::optipy::strip_pyo3(only_stubs))]
+            #[cfg_attr(feature = #stubs, ::pyo3_stub_gen::derive::#stubs_macro)]
+        });
+    }
+
+    // Leave the `PyO3` macro in place if it's already there, since it may have arguments.
+    let has_pyo3_macro = attrs_of(item).iter().any(|attr| {
+        attr.path()
+            .segments
+            .last()
+            .is_some_and(|last| last.ident == pyo3)
+    });
+    if !has_pyo3_macro {
+        attrs.extend(args.feature.as_ref().map_or_else(
+            || quote!(#[::pyo3::#pyo3_macro]),
+            |feature| quote!(#[cfg_attr(feature = #feature, ::pyo3::#pyo3_macro)]),
+        ));
+    }
+
+    Ok(quote! {
+        #attrs
+        #item
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use quote::quote;
+    use syn::{Item, parse_quote};
+
+    use super::{PythonArgs, expand};
+
+    #[test]
+    fn test_expand_python_and_stubs() {
+        let item: Item = parse_quote! {
+            #[pyclass(module = "yak_shaving")]
+            struct Yak;
+        };
+        let args = PythonArgs {
+            feature: Some(parse_quote!("python")),
+            stubs: Some(parse_quote!("stubs")),
+            strip: vec![quote!(items)],
+        };
+
+        let expected = quote! {
+            #[cfg_attr(not(feature = "python"), ::optipy::strip_pyo3(items))]
+            #[cfg_attr(
+                all(feature = "python", not(feature = "stubs")),
+                ::optipy::strip_pyo3(only_stubs)
+            )]
+            #[cfg_attr(feature = "stubs", ::pyo3_stub_gen::derive::gen_stub_pyclass)]
+            #[pyclass(module = "yak_shaving")]
+            struct Yak;
+        };
+        assert_eq!(
+            expand(&args, &item).unwrap().to_string(),
+            expected.to_string()
+        );
+    }
+
+    #[test]
+    fn test_expand_stubs_only() {
+        let item: Item = parse_quote! {
+            impl Yak {
+                fn shave(&mut self) {}
+            }
+        };
+        let args = PythonArgs {
+            stubs: Some(parse_quote!("stubs")),
+            ..PythonArgs::default()
+        };
+
+        let expected = quote! {
+            #[cfg_attr(not(feature = "stubs"), ::optipy::strip_pyo3(only_stubs))]
+            #[cfg_attr(feature = "stubs", ::pyo3_stub_gen::derive::gen_stub_pymethods)]
+            #[::pyo3::pymethods]
+            impl Yak {
+                fn shave(&mut self) {}
+            }
+        };
+        assert_eq!(
+            expand(&args, &item).unwrap().to_string(),
+            expected.to_string()
+        );
+    }
+
+    #[test]
+    fn test_expand_enums() {
+        let args = PythonArgs {
+            feature: Some(parse_quote!("python")),
+            stubs: Some(parse_quote!("stubs")),
+            ..PythonArgs::default()
+        };
+
+        let simple: Item = parse_quote!(
+            enum Tool {
+                Shears,
+                Clippers,
+            }
+        );
+        let expanded = expand(&args, &simple).unwrap().to_string();
+        assert!(expanded.contains("gen_stub_pyclass_enum"), "{expanded}");
+        assert!(
+            expanded.contains(r#"cfg_attr (feature = "python" , :: pyo3 :: pyclass)"#),
+            "{expanded}"
+        );
+
+        let complex: Item = parse_quote!(
+            enum Tool {
+                Shears { sharp: bool },
+                Clippers,
+            }
+        );
+        let expanded = expand(&args, &complex).unwrap().to_string();
+        assert!(
+            expanded.contains("gen_stub_pyclass_complex_enum"),
+            "{expanded}"
+        );
+    }
+
+    #[test]
+    fn test_expand_unsupported_item() {
+        let item: Item = parse_quote!(
+            const C: i32 = 1;
+        );
+        assert!(expand(&PythonArgs::default(), &item).is_err());
+    }
+}
@@ -17,9 +17,6 @@
 
 use pyo3::{PyResult, Python, prelude::*, pymodule, types::PyModule};
 
-#[cfg(feature = "stubs")]
-use pyo3_stub_gen::derive::{gen_stub_pyclass, gen_stub_pymethods};
-
 use rigetti_pyo3::{create_init_submodule, impl_repr};
 
 #[derive(Debug, thiserror::Error)]
@@ -27,7 +24,7 @@ use rigetti_pyo3::{create_init_submodule, impl_repr};
 pub struct CloggedClippersError;
 
 #[derive(Clone, Default, Debug)]
-#[cfg_attr(feature = "stubs", gen_stub_pyclass)]
+#[optipy::python(stubs = "stubs")]
 #[pyclass(module = "yak_shaving", skip_from_py_object)]
 pub struct Yak {
     is_shaved: bool,
@@ -41,8 +38,7 @@ impl Yak {
 
 impl_repr!(Yak);
 
-#[cfg_attr(not(feature = "stubs"), optipy::strip_pyo3(only_stubs))]
-#[cfg_attr(feature = "stubs", gen_stub_pymethods)]
+#[optipy::python(stubs = "stubs")]
 #[pymethods]
 impl Yak {
     #[new]
@@ -79,14 +75,13 @@ impl Yak {
 }
 
 #[derive(Clone, Default)]
-#[cfg_attr(feature = "stubs", gen_stub_pyclass)]
+#[optipy::python(stubs = "stubs")]
 #[pyclass(module = "yak_shaving", skip_from_py_object)]
 pub struct Clippers {
     is_clogged: bool,
 }
 
-#[cfg_attr(not(feature = "stubs"), optipy::strip_pyo3(only_stubs))]
-#[cfg_attr(feature = "stubs", gen_stub_pymethods)]
+#[optipy::python(stubs = "stubs")]
 #[pymethods]
 impl Clippers {
     #[new]
@@ -104,11 +99,11 @@ impl Clippers {
 }
 
 #[derive(Clone, Default)]
-#[cfg_attr(feature = "stubs", gen_stub_pyclass)]
+#[optipy::python(stubs = "stubs")]
 #[pyclass(module = "yak_shaving", skip_from_py_object, frozen)]
 pub struct Shears;
 
-#[cfg_attr(feature = "stubs", gen_stub_pymethods)]
+#[optipy::python(stubs = "stubs")]
 #[pymethods]
 impl Shears {
     #[new]
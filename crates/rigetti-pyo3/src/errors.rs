@@ -2,25 +2,97 @@
 
 #[cfg(not(feature = "stubs"))]
 /// Create a new Python exception.
+///
+/// The docstring may be given either as an expression after the base class,
+/// or as doc comments before the module.
+///
+/// The exception's constructor signature and attributes may be declared after the docstring,
+/// as `init: (...)` and `attributes: { ... }`. They're only used to generate stubs,
+/// so they're accepted and ignored when the "stubs" feature isn't active.
 #[macro_export]
 macro_rules! create_exception {
+    // Leading doc comments are collected one at a time,
+    // since an `expr` fragment could also start with an attribute.
+    ( @doc [$($doc:literal),*] #[doc = $next:literal] $($rest:tt)* ) => {
+        $crate::create_exception!(@doc [$($doc,)* $next] $($rest)*);
+    };
+    ( @doc [$($doc:literal),+] $module:expr, $py_err:ident, $base:ty $(, $($args:tt)*)? ) => {
+        $crate::create_exception!($module, $py_err, $base, concat!($($doc, "\n"),+) $(, $($args)*)?);
+    };
+    ( #[doc = $doc:literal] $($rest:tt)* ) => {
+        $crate::create_exception!(@doc [$doc] $($rest)*);
+    };
     ( $module:expr, $py_err:ident, $base:ty $(,)? ) => {
         $crate::create_exception!($module, $py_err, $base, "");
     };
     ( $module:expr, $py_err:ident, $base:ty, $doc:expr $(,)? ) => {
         $crate::pyo3::create_exception!($module, $py_err, $base, $doc);
     };
+    (
+        $module:expr, $py_err:ident, $base:ty, $doc:expr,
+        $(init: ( $($param:ident : $param_ty:ty $(= $param_default:literal)?),* $(,)? ) $(,)?)?
+        $(attributes: { $($(#[doc = $attr_doc:literal])* $attr:ident : $attr_ty:ty),* $(,)? } $(,)?)?
+    ) => {
+        $crate::pyo3::create_exception!($module, $py_err, $base, $doc);
+    };
 }
 
 #[cfg(feature = "stubs")]
 /// Create a new Python exception using the correct macro
 /// based on whether a "stubs" features is active.
+///
+/// The docstring may be given either as an expression after the base class,
+/// or as doc comments before the module; either way, it's used for both `__doc__` and the stub.
+///
+/// After the docstring, the exception's constructor signature and attributes can be declared
+/// so that its stub describes them. Constructor parameters may have a default value, given as
+/// a number or boolean literal, or as a string containing the Python expression that should
+/// appear in the stub.
+/// These declarations only affect the stub: the exception still accepts any arguments,
+/// and the attributes must be set on the exception value when it's raised.
+///
+/// # Example
+///
+/// ```
+/// use rigetti_pyo3::create_exception;
+/// use rigetti_pyo3::pyo3::exceptions::PyValueError;
+///
+/// create_exception!(
+///     /// Raised when a yak couldn't be shaved.
+///     yak_shaving,
+///     UnshavenYakError,
+///     PyValueError,
+///     init: (message: String, yak_id: u64 = 0, reason: Option<String> = "None"),
+///     attributes: {
+///         /// The ID of the yak that couldn't be shaved.
+///         yak_id: u64,
+///     },
+/// );
+/// ```
 #[macro_export]
 macro_rules! create_exception {
+    // Leading doc comments are collected one at a time,
+    // since an `expr` fragment could also start with an attribute.
+    ( @doc [$($doc:literal),*] #[doc = $next:literal] $($rest:tt)* ) => {
+        $crate::create_exception!(@doc [$($doc,)* $next] $($rest)*);
+    };
+    ( @doc [$($doc:literal),+] $module:expr, $py_err:ident, $base:ty $(, $($args:tt)*)? ) => {
+        $crate::create_exception!($module, $py_err, $base, concat!($($doc, "\n"),+) $(, $($args)*)?);
+    };
+    ( #[doc = $doc:literal] $($rest:tt)* ) => {
+        $crate::create_exception!(@doc [$doc] $($rest)*);
+    };
     ( $module:expr, $py_err:ident, $base:ty $(,)? ) => {
         $crate::create_exception!($module, $py_err, $base, "");
     };
     ( $module:expr, $py_err:ident, $base:ty, $doc:expr $(,)? ) => {
+        $crate::create_exception!($module, $py_err, $base, $doc, attributes: {});
+    };
+    (
+        $module:expr, $py_err:ident, $base:ty, $doc:expr,
+        $(init: ( $($param:ident : $param_ty:ty $(= $param_default:literal)?),* $(,)? ) $(,)?)?
+        $(attributes: { $($(#[doc = $attr_doc:literal])* $attr:ident : $attr_ty:ty),* $(,)? } $(,)?)?
+    ) => {
         $crate::pyo3::create_exception!($module, $py_err, $base, $doc);
 
         #[cfg(feature = "stubs")]
@@ -38,10 +110,18 @@ macro_rules! create_exception {
             $crate::pyo3_stub_gen::type_info::PyClassInfo {
                 pyclass_name: stringify!($py_err),
                 struct_id: ::std::any::TypeId::of::<$py_err>,
-                getters: &[],
+                getters: &[$($(
+                    $crate::pyo3_stub_gen::type_info::MemberInfo {
+                        name: stringify!($attr),
+                        r#type: <$attr_ty as $crate::pyo3_stub_gen::PyStubType>::type_output,
+                        doc: $crate::exception_docstring!(concat!($($attr_doc, "\n"),*)),
+                        default: None,
+                        deprecated: None,
+                    }
+                ),*)?],
                 setters: &[],
                 module: Some(stringify!($module)),
-                doc: $doc,
+                doc: $crate::exception_docstring!($doc),
                 bases: &[|| <$base as $crate::pyo3_stub_gen::PyStubType>::type_output()],
                 has_eq: false,
                 has_ord: false,
@@ -50,15 +130,127 @@ macro_rules! create_exception {
                 subclass: true,
             }
         }
+
+        $(
+        #[cfg(feature = "stubs")]
+        $crate::pyo3_stub_gen::inventory::submit! {
+            $crate::pyo3_stub_gen::type_info::PyMethodsInfo {
+                struct_id: ::std::any::TypeId::of::<$py_err>,
+                attrs: &[],
+                getters: &[],
+                setters: &[],
+                methods: &[$crate::pyo3_stub_gen::type_info::MethodInfo {
+                    name: "__init__",
+                    parameters: &[$(
+                        $crate::pyo3_stub_gen::type_info::ParameterInfo {
+                            name: stringify!($param),
+                            kind: $crate::pyo3_stub_gen::type_info::ParameterKind::PositionalOrKeyword,
+                            type_info: <$param_ty as $crate::pyo3_stub_gen::PyStubType>::type_input,
+                            default: $crate::exception_parameter_default!($($param_default)?),
+                        }
+                    ),*],
+                    r#return: $crate::pyo3_stub_gen::TypeInfo::none,
+                    doc: "",
+                    r#type: $crate::pyo3_stub_gen::type_info::MethodType::Instance,
+                    is_async: false,
+                    deprecated: None,
+                    type_ignored: None,
+                    is_overload: false,
+                }],
+                file: file!(),
+                line: line!(),
+                column: column!(),
+            }
+        }
+        )?
+    };
+}
+
+/// Produce the stub default value of a `create_exception!` constructor parameter.
+#[cfg(feature = "stubs")]
+#[doc(hidden)]
+#[macro_export]
+macro_rules! exception_parameter_default {
+    () => {
+        $crate::pyo3_stub_gen::type_info::ParameterDefault::None
+    };
+    ($default:literal) => {
+        $crate::pyo3_stub_gen::type_info::ParameterDefault::Expr {
+            // A string is the Python expression itself; other literals are written as in Python.
+            value: || match stringify!($default) {
+                "true" => ::std::string::String::from("True"),
+                "false" => ::std::string::String::from("False"),
+                _ => ::std::string::ToString::to_string(&$default),
+            },
+            source_module: None,
+        }
     };
 }
 
+/// Produce the stub docstring of a `create_exception!` class or attribute,
+/// without the leading space that each line of a doc comment keeps.
+#[cfg(feature = "stubs")]
+#[doc(hidden)]
+#[macro_export]
+macro_rules! exception_docstring {
+    ($doc:expr) => {{
+        const RAW: &str = $doc;
+        const STRIPPED: ([u8; RAW.len()], usize) = $crate::strip_doc_comment_indent(RAW);
+        const BYTES: &[u8] = &STRIPPED.0;
+        match ::std::str::from_utf8(BYTES.split_at(STRIPPED.1).0) {
+            Ok(doc) => doc,
+            Err(_) => panic!("removing spaces from a docstring can't make it invalid UTF-8"),
+        }
+    }};
+}
+
+/// Remove one space from the start of each line of `doc` if every non-empty line starts with one,
+/// as is the case for doc comments. Returns the result and its length in bytes.
+#[cfg(feature = "stubs")]
+#[must_use]
+pub const fn strip_doc_comment_indent<const N: usize>(doc: &str) -> ([u8; N], usize) {
+    let bytes = doc.as_bytes();
+    let mut indented = true;
+    let mut i = 0;
+    while i < bytes.len() {
+        let line_start = i == 0 || bytes[i - 1] == b'\n';
+        if line_start && bytes[i] != b'\n' && bytes[i] != b' ' {
+            indented = false;
+        }
+        i += 1;
+    }
+
+    let mut stripped = [0; N];
+    let mut len = 0;
+    let mut i = 0;
+    while i < bytes.len() {
+        let line_start = i == 0 || bytes[i - 1] == b'\n';
+        if !(indented && line_start && bytes[i] == b' ') {
+            stripped[len] = bytes[i];
+            len += 1;
+        }
+        i += 1;
+    }
+    (stripped, len)
+}
+
 /// Create a Python exception and a conversion from its Rust type.
 /// Note that the exception class must still be added to the module.
+///
+/// As with [`create_exception!`], the docstring may be given as doc comments instead,
+/// and any arguments after the docstring are passed on to [`create_exception!`].
 #[macro_export]
 macro_rules! exception {
-    ( $rust_err:ty, $module:expr, $py_err:ident, $base:ty $(, $doc:expr)? $(,)? ) => {
-        $crate::create_exception!( $module, $py_err, $base $(, $doc)? );
+    ( $(#[doc = $doc:literal])+ $rust_err:ty, $module:expr, $py_err:ident, $base:ty $(, $($stub_args:tt)*)? ) => {
+        $crate::exception!(
+            $rust_err, $module, $py_err, $base, concat!($($doc, "\n"),+) $(, $($stub_args)*)?
+        );
+    };
+    ( $rust_err:ty, $module:expr, $py_err:ident, $base:ty $(,)? ) => {
+        $crate::exception!($rust_err, $module, $py_err, $base, "");
+    };
+    ( $rust_err:ty, $module:expr, $py_err:ident, $base:ty, $doc:expr $(, $($stub_args:tt)*)? ) => {
+        $crate::create_exception!( $module, $py_err, $base, $doc $(, $($stub_args)*)? );
 
         #[doc = concat!(
             "Convert a Rust ",
@@ -73,3 +265,114 @@ macro_rules! exception {
         }
     };
 }
+
+#[cfg(all(test, feature = "stubs"))]
+mod tests {
+    use std::any::TypeId;
+
+    use pyo3::exceptions::PyValueError;
+    use pyo3_stub_gen::type_info::{ParameterDefault, PyClassInfo, PyMethodsInfo};
+
+    crate::create_exception!(
+        yak_shaving,
+        UnshavenYakError,
+        PyValueError,
+        "Raised when a yak couldn't be shaved.",
+        init: (
+            message: String,
+            yak_id: u64 = 0,
+            weight: f64 = 1.5,
+            trimmed: bool = false,
+            reason: Option<String> = "None",
+        ),
+        attributes: {
+            /// The ID of the yak that couldn't be shaved.
+            yak_id: u64,
+            reason: Option<String>,
+        },
+    );
+
+    crate::create_exception!(
+        /// Raised when the shears are too dull.
+        ///
+        /// Sharpen them and try again.
+        yak_shaving,
+        DullShearsError,
+        PyValueError,
+    );
+
+    #[test]
+    fn test_exception_doc_comments() {
+        const DOC: &str = "Raised when the shears are too dull.\n\nSharpen them and try again.\n";
+
+        let class = pyo3_stub_gen::inventory::iter::<PyClassInfo>
+            .into_iter()
+            .find(|info| info.pyclass_name == "DullShearsError")
+            .unwrap();
+        assert_eq!(class.doc, DOC);
+    }
+
+    #[test]
+    fn test_strip_doc_comment_indent() {
+        assert_eq!(
+            crate::exception_docstring!(" First line.\n\n     Indented code.\n"),
+            "First line.\n\n    Indented code.\n"
+        );
+        assert_eq!(
+            crate::exception_docstring!("First line.\n  Not a doc comment.\n"),
+            "First line.\n  Not a doc comment.\n"
+        );
+        assert_eq!(crate::exception_docstring!(""), "");
+    }
+
+    #[test]
+    fn test_exception_stub_info() {
+        let class = pyo3_stub_gen::inventory::iter::<PyClassInfo>
+            .into_iter()
+            .find(|info| info.pyclass_name == "UnshavenYakError")
+            .unwrap();
+        let getters: Vec<_> = class
+            .getters
+            .iter()
+            .map(|getter| (getter.name, (getter.r#type)().name, getter.doc))
+            .collect();
+        assert_eq!(
+            getters,
+            [
+                (
+                    "yak_id",
+                    "builtins.int".to_string(),
+                    "The ID of the yak that couldn't be shaved.\n"
+                ),
+                ("reason", "typing.Optional[builtins.str]".to_string(), ""),
+            ]
+        );
+
+        let methods = pyo3_stub_gen::inventory::iter::<PyMethodsInfo>
+            .into_iter()
+            .find(|info| (info.struct_id)() == TypeId::of::<UnshavenYakError>())
+            .unwrap();
+        let [init] = methods.methods else {
+            panic!("expected only `__init__`, got {:?}", methods.methods);
+        };
+        assert_eq!(init.name, "__init__");
+        let parameters: Vec<_> = init
+            .parameters
+            .iter()
+            .map(|parameter| match &parameter.default {
+                ParameterDefault::None => (parameter.name, None),
+                ParameterDefault::Expr { value, .. } => (parameter.name, Some(value())),
+            })
+            .collect();
+        assert_eq!(
+            parameters,
+            [
+                ("message", None),
+                ("yak_id", Some("0".to_string())),
+                ("weight", Some("1.5".to_string())),
+                ("trimmed", Some("False".to_string())),
+                ("reason", Some("None".to_string())),
+            ]
+        );
+    }
+}
@@ -29,7 +29,11 @@ pub mod stubs;
 #[cfg(feature = "async-tokio")]
 pub mod sync;
 mod traits;
+pub mod types;
 
+#[cfg(feature = "stubs")]
+#[doc(hidden)]
+pub use errors::strip_doc_comment_indent;
 #[cfg(feature = "async-tokio")]
 pub use pastey::paste;
 
@@ -202,9 +206,15 @@ mod test_pickle_roundtrip {
     /// Verify that we can pickle and unpickle complex enums
     #[test]
     fn test_enum_pickle_roundtrip() {
-        pyo3::append_to_inittab!(mymod);
         Python::initialize();
         Python::attach(|py| {
+            // Registered directly, since other tests may have already started the interpreter.
+            py.import("sys")
+                .unwrap()
+                .getattr("modules")
+                .unwrap()
+                .set_item("mymod", pyo3::wrap_pymodule!(mymod)(py))
+                .unwrap();
             let locals = PyDict::new(py);
             py_run!(
                 py,
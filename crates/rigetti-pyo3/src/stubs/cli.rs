@@ -0,0 +1,325 @@
+//! A reusable entry point for stub generation binaries.
+//!
+//! A crate's stub generation binary is usually a single line:
+//!
+//! ```rust,ignore
+//! // src/bin/stub_gen.rs
+//! rigetti_pyo3::stub_gen_main!(my_crate::stub_info);
+//! ```
+//!
+//! where `my_crate::stub_info` is defined with [`pyo3_stub_gen::define_stub_info_gatherer`].
+//! Run the binary with `--help` for its options.
+
+use std::{collections::HashSet, ffi::OsString, path::PathBuf, process::ExitCode};
+
+use pyo3_stub_gen::StubInfo;
+
+/// A post-processing pass, run on the gathered [`StubInfo`] before it is sorted and written.
+pub type StubPass = fn(&mut StubInfo);
+
+/// The usage message printed for `--help` and for invalid arguments.
+const USAGE: &str = "\
+Generate Python stub files for this crate.
+
+Usage: stub_gen [OPTIONS]
+
+Options:
+  -o, --output-dir <DIR>  Write stubs under DIR instead of the configured Python root
+      --check             Don't write anything; exit with an error if the stubs are out of date
+  -m, --module <NAME>     Only generate the module NAME and its submodules (repeatable)
+  -h, --help              Print this message
+";
+
+/// The command-line arguments accepted by [`main`].
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct StubGenArgs {
+    /// Write stubs under this directory instead of [`StubInfo::python_root`].
+    pub output_dir: Option<PathBuf>,
+    /// Compare the stubs against the files on disk instead of writing them.
+    pub check: bool,
+    /// Only generate these modules and their submodules; all modules if empty.
+    pub modules: Vec<String>,
+    /// Print usage information instead of generating stubs.
+    pub help: bool,
+}
+
+impl StubGenArgs {
+    /// Parse arguments, not including the program name.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error for unknown arguments, options missing their values,
+    /// and flags given a value, e.g. `--check=false`.
+    pub fn parse<I>(args: I) -> pyo3_stub_gen::Result<Self>
+    where
+        I: IntoIterator,
+        I::Item: Into<OsString>,
+    {
+        let mut parsed = Self::default();
+        let mut args = args.into_iter().map(Into::into);
+
+        while let Some(arg) = args.next() {
+            let arg = arg
+                .into_string()
+                .map_err(|arg| anyhow::anyhow!("invalid argument: {}", arg.display()))?;
+
+            // Support both `--option value` and `--option=value`.
+            let (flag, inline_value) = match arg.split_once('=') {
+                Some((flag, value)) if flag.starts_with("--") => (flag, Some(value.to_string())),
+                _ => (arg.as_str(), None),
+            };
+            let mut value = |flag: &str| {
+                inline_value
+                    .clone()
+                    .map(OsString::from)
+                    .or_else(|| args.next())
+                    .ok_or_else(|| anyhow::anyhow!("missing value for {flag}"))
+            };
+
+            let no_value = |flag: &str| match &inline_value {
+                Some(_) => Err(anyhow::anyhow!("{flag} does not take a value")),
+                None => Ok(true),
+            };
+
+            match flag {
+                "-o" | "--output-dir" => parsed.output_dir = Some(value(flag)?.into()),
+                "--check" => parsed.check = no_value(flag)?,
+                "-m" | "--module" => parsed.modules.push(
+                    value(flag)?
+                        .into_string()
+                        .map_err(|name| anyhow::anyhow!("invalid module: {}", name.display()))?,
+                ),
+                "-h" | "--help" => parsed.help = no_value(flag)?,
+                _ => anyhow::bail!("unexpected argument: {arg}"),
+            }
+        }
+
+        Ok(parsed)
+    }
+
+    /// Parse the arguments this process was started with.
+    ///
+    /// # Errors
+    ///
+    /// See [`StubGenArgs::parse`].
+    pub fn from_env() -> pyo3_stub_gen::Result<Self> {
+        Self::parse(std::env::args_os().skip(1))
+    }
+}
+
+/// Apply `passes` and `args` to `stub`, then write the stubs or check them against the files on disk.
+///
+/// Passes run in order, after module filtering and before sorting.
+/// In `--check` mode, any differences are printed to stderr.
+///
+/// # Errors
+///
+/// Returns an error if the stubs can't be written, or can't be compared against the files on disk.
+pub fn run(
+    mut stub: StubInfo,
+    args: &StubGenArgs,
+    passes: &[StubPass],
+) -> pyo3_stub_gen::Result<ExitCode> {
+    if !args.modules.is_empty() {
+        stub.modules.retain(|name, _| {
+            args.modules.iter().any(|module| {
+                name == module
+                    || name
+                        .strip_prefix(module.as_str())
+                        .is_some_and(|rest| rest.starts_with('.'))
+            })
+        });
+
+        // Don't import submodules that won't be generated.
+        let kept: HashSet<String> = stub.modules.keys().cloned().collect();
+        for (name, module) in &mut stub.modules {
+            module
+                .submodules
+                .retain(|submodule| kept.contains(&format!("{name}.{submodule}")));
+        }
+    }
+
+    if let Some(output_dir) = &args.output_dir {
+        stub.python_root.clone_from(output_dir);
+    }
+
+    for pass in passes {
+        pass(&mut stub);
+    }
+    super::sort(&mut stub);
+
+    if args.check {
+        let diff = super::verify(&stub)?;
+        if !diff.is_empty() {
+            eprint!("{diff}");
+        }
+        Ok(diff.exit_code())
+    } else {
+        stub.generate()?;
+        Ok(ExitCode::SUCCESS)
+    }
+}
+
+/// The body of a stub generation binary; see [`stub_gen_main!`](crate::stub_gen_main).
+///
+/// Parses the process arguments, gathers the stubs with `stub_info`, and calls [`run`],
+/// reporting any errors to stderr.
+pub fn main(stub_info: fn() -> pyo3_stub_gen::Result<StubInfo>, passes: &[StubPass]) -> ExitCode {
+    let args = match StubGenArgs::from_env() {
+        Ok(args) if args.help => {
+            print!("{USAGE}");
+            return ExitCode::SUCCESS;
+        }
+        Ok(args) => args,
+        Err(err) => {
+            eprintln!("error: {err}\n\n{USAGE}");
+            return ExitCode::FAILURE;
+        }
+    };
+
+    match stub_info().and_then(|stub| run(stub, &args, passes)) {
+        Ok(code) => code,
+        Err(err) => {
+            eprintln!("error: {err:#}");
+            ExitCode::FAILURE
+        }
+    }
+}
+
+/// Define the `main` function of a stub generation binary.
+///
+/// The first argument is a function returning the crate's [`StubInfo`],
+/// usually defined with [`pyo3_stub_gen::define_stub_info_gatherer`].
+/// It can optionally be followed by a list of [`StubPass`]es to run before the stubs are written.
+/// See [`stubs::main`](crate::stubs::main) for details.
+///
+/// # Example
+///
+/// ```rust,no_run
+/// // src/bin/stub_gen.rs
+/// # mod my_crate {
+/// #     pub fn stub_info() -> pyo3_stub_gen::Result<pyo3_stub_gen::StubInfo> { unimplemented!() }
+/// # }
+/// fn drop_private_functions(stub: &mut pyo3_stub_gen::StubInfo) {
+///     for module in stub.modules.values_mut() {
+///         module.function.retain(|name, _| !name.starts_with('_'));
+///     }
+/// }
+///
+/// rigetti_pyo3::stub_gen_main!(my_crate::stub_info, passes: [drop_private_functions]);
+/// ```
+#[macro_export]
+macro_rules! stub_gen_main {
+    ( $stub_info:path $(, passes: [ $($pass:path),* $(,)? ])? $(,)? ) => {
+        fn main() -> ::std::process::ExitCode {
+            $crate::stubs::main($stub_info, &[$($($pass),*)?])
+        }
+    };
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{fs, path::PathBuf, process::ExitCode};
+
+    use pyo3_stub_gen::{StubGenConfig, StubInfo, generate::Module};
+
+    use super::{StubGenArgs, run};
+
+    #[test]
+    fn test_parse_args() {
+        let args = StubGenArgs::parse([
+            "--check",
+            "-o",
+            "python",
+            "--module=mymod.sub",
+            "-m",
+            "other",
+        ])
+        .unwrap();
+        assert_eq!(
+            args,
+            StubGenArgs {
+                output_dir: Some("python".into()),
+                check: true,
+                modules: vec!["mymod.sub".into(), "other".into()],
+                help: false,
+            }
+        );
+
+        assert!(StubGenArgs::parse(["--output-dir"]).is_err());
+        assert!(StubGenArgs::parse(["--unknown"]).is_err());
+        for flag in ["--check=false", "--help=x"] {
+            let error = StubGenArgs::parse([flag]).unwrap_err().to_string();
+            assert!(error.ends_with("does not take a value"), "{error}");
+        }
+    }
+
+    /// A mixed layout stub, with a submodule, that writes to nowhere in particular.
+    fn stub_info() -> StubInfo {
+        let module = |name: &str| Module {
+            doc: format!("The {name} module."),
+            name: name.into(),
+            default_module_name: "mymod".into(),
+            ..Module::default()
+        };
+
+        let mut mymod = module("mymod");
+        mymod.submodules.insert("sub".into());
+        let mut sub = module("mymod.sub");
+        sub.submodules.extend(["nested".into(), "unused".into()]);
+
+        StubInfo {
+            modules: [
+                ("mymod".to_string(), mymod),
+                ("mymod.sub".to_string(), sub),
+                ("mymod.sub.nested".to_string(), module("mymod.sub.nested")),
+                ("mymodule".to_string(), module("mymodule")),
+            ]
+            .into(),
+            python_root: PathBuf::from("/nonexistent"),
+            is_mixed_layout: true,
+            config: StubGenConfig::default(),
+            pyproject_dir: None,
+            default_module_name: "mymod".into(),
+            project_name: "mymod".into(),
+        }
+    }
+
+    #[test]
+    fn test_run_filters_modules_and_applies_passes() {
+        let output_dir =
+            std::env::temp_dir().join(format!("rigetti-pyo3-stub-gen-{}", std::process::id()));
+        let _ = fs::remove_dir_all(&output_dir);
+
+        let args = StubGenArgs {
+            output_dir: Some(output_dir.clone()),
+            modules: vec!["mymod.sub".into()],
+            ..StubGenArgs::default()
+        };
+        let passes: &[super::StubPass] = &[|stub| {
+            for module in stub.modules.values_mut() {
+                module.doc.push_str(" Post-processed.");
+            }
+        }];
+
+        assert_eq!(run(stub_info(), &args, passes).unwrap(), ExitCode::SUCCESS);
+        let stub = fs::read_to_string(output_dir.join("mymod/sub/__init__.pyi")).unwrap();
+        assert!(
+            stub.contains("The mymod.sub module. Post-processed."),
+            "{stub}"
+        );
+        assert!(stub.contains("from . import nested"), "{stub}");
+        assert!(!stub.contains("unused"), "{stub}");
+        assert!(output_dir.join("mymod/sub/nested/__init__.pyi").exists());
+        assert!(!output_dir.join("mymod/__init__.pyi").exists());
+
+        let check = StubGenArgs {
+            check: true,
+            ..args
+        };
+        assert_eq!(run(stub_info(), &check, passes).unwrap(), ExitCode::SUCCESS);
+        assert_eq!(run(stub_info(), &check, &[]).unwrap(), ExitCode::FAILURE);
+
+        fs::remove_dir_all(&output_dir).unwrap();
+    }
+}
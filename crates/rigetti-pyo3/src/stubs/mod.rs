@@ -1,10 +1,17 @@
-//! Provide support for sorting `.pyi` stubs.
+//! Provide support for sorting and verifying `.pyi` stubs.
 //!
 //! [`pyo3-stub-gen`] doesn't keep its output in a consistent order.
 //! Thankfully the order is deterministic if the program is deterministic,
 //! but due to the use of the [`inventory`][] crate, it's not deterministic if the code changes.
 //! This module thus sorts the components of stubs that are not already in a fixed order.
 //!
+//! Overloads can't be sorted arbitrarily, since they're matched in order.
+//! Those of module-level functions are put in source order, but [`pyo3-stub-gen`] records no
+//! source location for methods, so each method's overloads keep the order in which they were
+//! gathered. That's the order they're written in when they come from a single `#[pymethods]`
+//! block or stub snippet, but overloads of one method spread across several may still be
+//! reordered by unrelated changes to the code.
+//!
 //! [`pyo3-stub-gen`]: https://github.com/Jij-Inc/pyo3-stub-gen
 //!
 //! # Example Usage
@@ -28,6 +35,9 @@
 //!    Ok(())
 //! }
 //! ```
+//!
+//! To check that committed stubs are up to date without writing anything, see [`verify`].
+//! To do all of the above from a stub generation binary, see [`stub_gen_main!`](crate::stub_gen_main).
 
 use std::{
     cmp::Ordering,
@@ -39,15 +49,22 @@ use itertools::Itertools as _;
 use pyo3_stub_gen::{
     ImportKind, StubInfo, TypeIdentifierRef, TypeInfo,
     generate::{
-        ClassDef, EnumDef, MemberDef, MethodDef, MethodType, Module, Parameter, ParameterDefault,
-        Parameters,
+        ClassDef, EnumDef, FunctionDef, MemberDef, MethodDef, MethodType, Module, ModuleReExport,
+        Parameter, ParameterDefault, Parameters,
     },
     type_info::{DeprecatedInfo, IgnoreTarget, ParameterKind},
 };
 
+mod cli;
+mod verify;
+
+pub use cli::{StubGenArgs, StubPass, main, run};
+pub use verify::{StubDiff, StubMismatch, render, verify};
+
 /// Sort, in place, all the unsorted components of a [`StubInfo`].
 ///
-/// See the module-level documentation for more information.
+/// See the module-level documentation for more information,
+/// including why the overloads of a method aren't sorted.
 pub fn sort(stub: &mut StubInfo) {
     let StubInfo {
         modules,
@@ -311,16 +328,17 @@ fn sort_class(class: &mut ClassDef) {
         name: _, // These strings don't need adjustment.
         module: _,
         doc: _,
-        attrs: _, // Regardless of the type of the field, we can't reorder attributes.
+        attrs,    // Class attributes are independent declarations, so their order is arbitrary.
         bases: _, // Regardless of the type of the field, we can't reorder base classes.
         classes,
         match_args: _, // Regardless of the type of the field, we can't reorder match args.
         subclass: _,
-        methods, // A map from names to overload sets; overloads can't be reordered
+        methods, // A map from names to overload sets, which keep the order they were gathered in
         getter_setters,
     } = class;
 
     // [`MemberDef`]s are atomic and don't have contents that need to be sorted.
+    sort_members(attrs);
     methods.sort_by(cmp_strings);
     getter_setters.sort_by(cmp_strings);
 
@@ -338,17 +356,58 @@ fn sort_enum(r#enum: &mut EnumDef) {
         doc: _,
         variants: _, // Regardless of the type of the field, we can't reorder the variants.
         methods,
-        attrs: _, // Regardless of the type of the field, we can't reorder the variants attributes.
+        attrs, // Class attributes are independent declarations, so their order is arbitrary.
         getters,
         setters,
     } = r#enum;
 
-    // [`MethodDef`]s and [`MemberDef`]s are atomic and don't have contents that need to be sorted.
-    methods.sort_by(ArbitraryOrd::cmp);
+    // [`MethodDef`]s are atomic, but methods sharing a name form an overload set,
+    // so we only sort by name; the sort is stable, which keeps each overload set in order.
+    methods.sort_by_key(|method| method.name);
+
+    // [`MemberDef`]s are atomic and don't have contents that need to be sorted.
+    sort_members(attrs);
     getters.sort_by(ArbitraryOrd::cmp);
     setters.sort_by(ArbitraryOrd::cmp);
 }
 
+/// Sort a list of [`MemberDef`]s by name, falling back to the rest of their contents.
+fn sort_members(members: &mut [MemberDef]) {
+    members.sort_by(|left, right| {
+        left.name
+            .cmp(right.name)
+            .then_with(|| ArbitraryOrd::cmp(left, right))
+    });
+}
+
+/// Put an overload set of module-level functions in source order.
+///
+/// Overloads are matched in order, so they can't be sorted arbitrarily;
+/// this is the same order [`pyo3_stub_gen`] uses when writing the stub file,
+/// applied to the [`StubInfo`] itself so that it's stable, too.
+fn sort_overloads(functions: &mut [FunctionDef]) {
+    functions.sort_by_key(|function| {
+        (
+            function.file,
+            function.line,
+            function.column,
+            function.index,
+        )
+    });
+}
+
+/// Sort a module re-export's items and additional items.
+fn sort_re_export(re_export: &mut ModuleReExport) {
+    let ModuleReExport {
+        source_module: _, // This string doesn't need adjustment.
+        items,            // Empty means a wildcard, which sorting preserves.
+        additional_items,
+    } = re_export;
+
+    items.sort();
+    additional_items.sort();
+}
+
 /// Sort elements of a module definition.
 fn sort_module(module: &mut Module) {
     // Extract and sort fields with nested contents that need to be sorted (then sort them).
@@ -360,15 +419,17 @@ fn sort_module(module: &mut Module) {
     let Module {
         class,
         enum_,
+        function,
+        module_re_exports,
 
-        // Most fields need no internal adjustment.
-        module_re_exports: _,
-        function: _,
+        // These are all maps or sets with atomic contents, so they're already in sorted order.
         variables: _,
         type_aliases: _,
         submodules: _,
         verbatim_all_entries: _,
         excluded_all_entries: _,
+
+        // Most fields need no internal adjustment.
         doc: _,
         name: _,
         default_module_name: _,
@@ -376,4 +437,280 @@ fn sort_module(module: &mut Module) {
 
     class.values_mut().for_each(sort_class);
     enum_.values_mut().for_each(sort_enum);
+
+    // Function names are already sorted by the map, but the overload sets they name are not.
+    for functions in function.values_mut() {
+        sort_overloads(functions);
+    }
+
+    module_re_exports.iter_mut().for_each(sort_re_export);
+    module_re_exports.sort_by(|left, right| {
+        (&left.source_module, &left.items, &left.additional_items).cmp(&(
+            &right.source_module,
+            &right.items,
+            &right.additional_items,
+        ))
+    });
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{any::TypeId, path::PathBuf};
+
+    use indexmap::IndexMap;
+    use pyo3_stub_gen::{
+        StubGenConfig, StubInfo, TypeInfo,
+        generate::{
+            ClassDef, EnumDef, FunctionDef, MemberDef, MethodDef, MethodType, Module,
+            ModuleReExport, Parameters,
+        },
+    };
+
+    use super::sort;
+
+    fn member(name: &'static str) -> MemberDef {
+        MemberDef {
+            name,
+            r#type: TypeInfo::any(),
+            doc: "",
+            default: None,
+            deprecated: None,
+        }
+    }
+
+    fn method(name: &'static str, doc: &'static str) -> MethodDef {
+        MethodDef {
+            name,
+            parameters: Parameters::new(),
+            r#return: TypeInfo::none(),
+            doc,
+            r#type: MethodType::Instance,
+            is_async: false,
+            deprecated: None,
+            type_ignored: None,
+            is_overload: true,
+        }
+    }
+
+    fn function(name: &'static str, line: u32) -> FunctionDef {
+        FunctionDef {
+            name,
+            parameters: Parameters::new(),
+            r#return: TypeInfo::none(),
+            doc: "",
+            is_async: false,
+            deprecated: None,
+            type_ignored: None,
+            is_overload: true,
+            file: "src/lib.rs",
+            line,
+            column: 1,
+            index: 0,
+        }
+    }
+
+    fn re_export(source_module: &str, items: &[&str]) -> ModuleReExport {
+        ModuleReExport {
+            source_module: source_module.into(),
+            items: items.iter().map(ToString::to_string).collect(),
+            additional_items: Vec::new(),
+        }
+    }
+
+    fn stub_info(module: Module) -> StubInfo {
+        StubInfo {
+            modules: [("mymod".to_string(), module)].into(),
+            python_root: PathBuf::default(),
+            is_mixed_layout: false,
+            config: StubGenConfig::default(),
+            pyproject_dir: None,
+            default_module_name: "mymod".into(),
+            project_name: "mymod".into(),
+        }
+    }
+
+    /// A module with every field that needs sorting populated out of order.
+    fn unsorted_module() -> Module {
+        let class = ClassDef {
+            name: "Foo",
+            module: Some("mymod"),
+            doc: "",
+            attrs: vec![member("ZERO"), member("ONE"), member("MANY")],
+            getter_setters: IndexMap::new(),
+            methods: IndexMap::new(),
+            bases: Vec::new(),
+            classes: Vec::new(),
+            match_args: None,
+            subclass: false,
+        };
+
+        let r#enum = EnumDef {
+            name: "Bar",
+            module: Some("mymod"),
+            doc: "",
+            variants: &[],
+            methods: vec![
+                method("scale", "int"),
+                method("add", "int"),
+                method("scale", "float"),
+                method("add", "float"),
+            ],
+            attrs: vec![member("B"), member("A")],
+            getters: Vec::new(),
+            setters: Vec::new(),
+        };
+
+        Module {
+            class: [(TypeId::of::<u8>(), class)].into(),
+            enum_: [(TypeId::of::<u16>(), r#enum)].into(),
+            function: [(
+                "parse",
+                vec![
+                    function("parse", 30),
+                    function("parse", 10),
+                    function("parse", 20),
+                ],
+            )]
+            .into(),
+            module_re_exports: vec![
+                re_export("mymod.zeta", &["Z", "A"]),
+                re_export("mymod.alpha", &[]),
+            ],
+            name: "mymod".into(),
+            default_module_name: "mymod".into(),
+            ..Module::default()
+        }
+    }
+
+    fn sorted_module(module: Module) -> Module {
+        let mut stub = stub_info(module);
+        sort(&mut stub);
+        stub.modules.remove("mymod").unwrap()
+    }
+
+    #[test]
+    fn test_sort_class_attrs() {
+        let module = sorted_module(unsorted_module());
+        let names: Vec<_> = module
+            .class
+            .values()
+            .next()
+            .unwrap()
+            .attrs
+            .iter()
+            .map(|attr| attr.name)
+            .collect();
+        assert_eq!(names, ["MANY", "ONE", "ZERO"]);
+    }
+
+    #[test]
+    fn test_sort_enum_preserves_overload_order() {
+        let module = sorted_module(unsorted_module());
+        let r#enum = module.enum_.values().next().unwrap();
+
+        let methods: Vec<_> = r#enum
+            .methods
+            .iter()
+            .map(|method| (method.name, method.doc))
+            .collect();
+        assert_eq!(
+            methods,
+            [
+                ("add", "int"),
+                ("add", "float"),
+                ("scale", "int"),
+                ("scale", "float")
+            ]
+        );
+
+        let attrs: Vec<_> = r#enum.attrs.iter().map(|attr| attr.name).collect();
+        assert_eq!(attrs, ["A", "B"]);
+    }
+
+    #[test]
+    fn test_sort_function_overloads_by_source_location() {
+        let module = sorted_module(unsorted_module());
+        let lines: Vec<_> = module.function["parse"]
+            .iter()
+            .map(|function| function.line)
+            .collect();
+        assert_eq!(lines, [10, 20, 30]);
+    }
+
+    #[test]
+    fn test_sort_module_re_exports() {
+        let module = sorted_module(unsorted_module());
+        assert_eq!(
+            module.module_re_exports,
+            [
+                re_export("mymod.alpha", &[]),
+                re_export("mymod.zeta", &["A", "Z"])
+            ]
+        );
+    }
+
+    /// Methods have no source location, so unlike functions,
+    /// their overloads can only be kept in the order they were gathered in.
+    #[test]
+    fn test_sort_keeps_gathered_order_of_method_overloads() {
+        let mut module = unsorted_module();
+        for class in module.class.values_mut() {
+            class.methods.insert(
+                "scale".into(),
+                vec![method("scale", "float"), method("scale", "int")],
+            );
+            class
+                .methods
+                .insert("add".into(), vec![method("add", "int")]);
+        }
+        for r#enum in module.enum_.values_mut() {
+            r#enum.methods.swap(0, 2);
+        }
+
+        let module = sorted_module(module);
+        let class = module.class.values().next().unwrap();
+        let methods: Vec<_> = class
+            .methods
+            .iter()
+            .flat_map(|(name, overloads)| {
+                overloads
+                    .iter()
+                    .map(move |method| (name.as_str(), method.doc))
+            })
+            .collect();
+        assert_eq!(
+            methods,
+            [("add", "int"), ("scale", "float"), ("scale", "int")]
+        );
+
+        let r#enum = module.enum_.values().next().unwrap();
+        let overloads: Vec<_> = r#enum
+            .methods
+            .iter()
+            .filter(|method| method.name == "scale")
+            .map(|method| method.doc)
+            .collect();
+        assert_eq!(overloads, ["float", "int"]);
+    }
+
+    /// Reordering anything other than the overloads of a method doesn't change the result.
+    #[test]
+    fn test_sort_is_independent_of_registration_order() {
+        let mut shuffled = unsorted_module();
+        for class in shuffled.class.values_mut() {
+            class.attrs.reverse();
+        }
+        for r#enum in shuffled.enum_.values_mut() {
+            // Interleave the overload sets differently, but keep each one in order.
+            r#enum.methods.swap(0, 1);
+            r#enum.methods.swap(2, 3);
+            r#enum.attrs.reverse();
+        }
+        for functions in shuffled.function.values_mut() {
+            functions.reverse();
+        }
+        shuffled.module_re_exports.reverse();
+
+        assert_eq!(sorted_module(shuffled), sorted_module(unsorted_module()));
+    }
 }
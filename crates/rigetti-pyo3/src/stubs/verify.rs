@@ -0,0 +1,388 @@
+//! Check that committed stub files match what [`StubInfo::generate`] would write.
+//!
+//! Generated `.pyi` files are usually committed alongside the bindings they describe,
+//! which makes it easy to change the bindings and forget to regenerate the stubs.
+//! [`verify`] renders the stubs in memory and compares them against the files on disk
+//! without touching them, so a test can fail whenever the committed stubs are stale:
+//!
+//! ```rust,no_run
+//! # fn stub_info() -> pyo3_stub_gen::Result<pyo3_stub_gen::generate::StubInfo> { unimplemented!() }
+//! #[test]
+//! fn stubs_are_up_to_date() -> pyo3_stub_gen::Result<()> {
+//!     let diff = rigetti_pyo3::stubs::verify(&stub_info()?)?;
+//!     assert!(diff.is_empty(), "{diff}");
+//!     Ok(())
+//! }
+//! ```
+
+use std::{
+    collections::BTreeMap,
+    fmt, fs, io,
+    path::{Path, PathBuf},
+    process::ExitCode,
+};
+
+use pyo3_stub_gen::StubInfo;
+
+/// Render every file that [`StubInfo::generate`] would write for `stub`, without writing them.
+///
+/// The stub is [sorted](super::sort) first, so the output is stable across changes to the order
+/// in which items are registered. Each module is formatted in memory the same way
+/// [`StubInfo::generate`] formats it: as a `.pyi` stub for modules in the `PyO3` library,
+/// or as an `__init__.py` re-exporting them for pure Python modules in a mixed layout.
+/// The result maps each destination path to its expected contents.
+/// Documentation generation (`doc_gen`) is not rendered.
+///
+/// # Errors
+///
+/// Returns the same errors [`StubInfo::generate`] would,
+/// e.g. when a pure Rust layout contains more than one module.
+pub fn render(stub: &StubInfo) -> pyo3_stub_gen::Result<BTreeMap<PathBuf, String>> {
+    let mut stub = stub.clone();
+    super::sort(&mut stub);
+
+    if !stub.is_mixed_layout && stub.modules.len() > 1 {
+        anyhow::bail!(
+            "Pure Rust layout does not support multiple modules or submodules. Found {} modules: {}.",
+            stub.modules.len(),
+            stub.modules
+                .keys()
+                .map(|name| format!("'{name}'"))
+                .collect::<Vec<_>>()
+                .join(", ")
+        );
+    }
+
+    let mut files = BTreeMap::new();
+    for (name, module) in &stub.modules {
+        if module.is_empty() {
+            continue;
+        }
+
+        let normalized_name = name.replace('-', "_");
+        let path = normalized_name.replace('.', "/");
+
+        if !stub.is_mixed_layout {
+            let package_name = normalized_name
+                .split('.')
+                .next()
+                .filter(|package_name| !package_name.is_empty())
+                .ok_or_else(|| {
+                    anyhow::anyhow!(
+                        "Module name is empty after normalization: original name was `{name}`"
+                    )
+                })?;
+            files.insert(
+                stub.python_root.join(format!("{package_name}.pyi")),
+                module.format_with_config(stub.config.use_type_statement),
+            );
+        } else if is_pyo3_generated(&stub, &normalized_name) {
+            files.insert(
+                stub.python_root.join(&path).join("__init__.pyi"),
+                module.format_with_config(stub.config.use_type_statement),
+            );
+        } else if !module.is_init_py_compatible() {
+            anyhow::bail!(
+                "Module '{name}' has PyO3 items (classes, functions, etc.) but is not under \
+                 the PyO3 module path '{}'.",
+                stub.default_module_name
+            );
+        } else if !stub.config.generate_init_py.is_enabled_for(name) {
+            anyhow::bail!(
+                "Module '{name}' is not a PyO3 module and requires `generate-init-py` to be enabled."
+            );
+        } else {
+            files.insert(
+                stub.python_root.join(&path).join("__init__.py"),
+                module.format_init_py(),
+            );
+        }
+    }
+    Ok(files)
+}
+
+/// Whether the module `normalized_name` is part of the `PyO3` library in a mixed layout,
+/// i.e. is at or below the stub's default module.
+fn is_pyo3_generated(stub: &StubInfo, normalized_name: &str) -> bool {
+    let default_module_name = stub.default_module_name.replace('-', "_");
+    normalized_name
+        .strip_prefix(&default_module_name)
+        .is_some_and(|rest| rest.is_empty() || rest.starts_with('.'))
+}
+
+/// Compare the files [`StubInfo::generate`] would write for `stub` against those on disk.
+///
+/// The files on disk are not modified; see [`render`] for how the expected contents are
+/// produced.
+///
+/// # Errors
+///
+/// Returns an error if the stubs can't be rendered,
+/// or if an existing stub file can't be read.
+pub fn verify(stub: &StubInfo) -> pyo3_stub_gen::Result<StubDiff> {
+    let mut mismatches = Vec::new();
+
+    for (path, expected) in render(stub)? {
+        match fs::read_to_string(&path) {
+            Ok(actual) if actual == expected => {}
+            Ok(actual) => mismatches.push(StubMismatch::Changed {
+                path,
+                expected,
+                actual,
+            }),
+            Err(err) if err.kind() == io::ErrorKind::NotFound => {
+                mismatches.push(StubMismatch::Missing { path, expected });
+            }
+            Err(err) => {
+                return Err(anyhow::Error::new(err)
+                    .context(format!("failed to read stub file {}", path.display())));
+            }
+        }
+    }
+
+    Ok(StubDiff { mismatches })
+}
+
+/// The differences between the generated stubs and the files on disk, as found by [`verify`].
+///
+/// The [`Display`](fmt::Display) implementation renders a summary
+/// with the changed lines of each file, suitable for a test failure message.
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct StubDiff {
+    /// The stale or missing files, in path order.
+    pub mismatches: Vec<StubMismatch>,
+}
+
+impl StubDiff {
+    /// Whether the files on disk are up to date.
+    #[must_use]
+    pub const fn is_empty(&self) -> bool {
+        self.mismatches.is_empty()
+    }
+
+    /// [`ExitCode::SUCCESS`] if the files on disk are up to date, [`ExitCode::FAILURE`] otherwise.
+    #[must_use]
+    pub const fn exit_code(&self) -> ExitCode {
+        if self.is_empty() {
+            ExitCode::SUCCESS
+        } else {
+            ExitCode::FAILURE
+        }
+    }
+}
+
+impl fmt::Display for StubDiff {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        if self.is_empty() {
+            return writeln!(f, "stubs are up to date");
+        }
+
+        writeln!(
+            f,
+            "{} stub file(s) out of date; regenerate the stubs:",
+            self.mismatches.len()
+        )?;
+        self.mismatches
+            .iter()
+            .try_for_each(|mismatch| write!(f, "{mismatch}"))
+    }
+}
+
+/// A single stub file that doesn't match its generated contents.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum StubMismatch {
+    /// The file would be generated, but doesn't exist.
+    Missing {
+        /// Where the file would be written.
+        path: PathBuf,
+        /// The generated contents.
+        expected: String,
+    },
+    /// The file exists, but its contents differ from the generated contents.
+    Changed {
+        /// The path of the file.
+        path: PathBuf,
+        /// The generated contents.
+        expected: String,
+        /// The contents on disk.
+        actual: String,
+    },
+}
+
+impl StubMismatch {
+    /// The path of the mismatched file.
+    #[must_use]
+    pub fn path(&self) -> &Path {
+        match self {
+            Self::Missing { path, .. } | Self::Changed { path, .. } => path,
+        }
+    }
+}
+
+impl fmt::Display for StubMismatch {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Missing { path, .. } => writeln!(f, "--- {} (missing)", path.display()),
+            Self::Changed {
+                path,
+                expected,
+                actual,
+            } => {
+                writeln!(f, "--- {} (on disk)", path.display())?;
+                writeln!(f, "+++ {} (generated)", path.display())?;
+
+                // Report the single hunk between the longest common prefix and suffix.
+                // This isn't a minimal diff, but it's enough to point at what changed.
+                let old: Vec<_> = actual.lines().collect();
+                let new: Vec<_> = expected.lines().collect();
+                let prefix = old
+                    .iter()
+                    .zip(&new)
+                    .take_while(|(old, new)| old == new)
+                    .count();
+                let suffix = old[prefix..]
+                    .iter()
+                    .rev()
+                    .zip(new[prefix..].iter().rev())
+                    .take_while(|(old, new)| old == new)
+                    .count();
+                let old = &old[prefix..old.len() - suffix];
+                let new = &new[prefix..new.len() - suffix];
+
+                writeln!(
+                    f,
+                    "@@ -{},{} +{},{} @@",
+                    prefix + 1,
+                    old.len(),
+                    prefix + 1,
+                    new.len()
+                )?;
+                old.iter().try_for_each(|line| writeln!(f, "-{line}"))?;
+                new.iter().try_for_each(|line| writeln!(f, "+{line}"))
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{fs, path::PathBuf};
+
+    use pyo3_stub_gen::{StubGenConfig, StubInfo, generate::Module};
+
+    use super::{StubMismatch, render, verify};
+
+    /// A pure Rust layout stub rooted in a fresh temporary directory.
+    fn stub_info(test_name: &str) -> StubInfo {
+        let python_root: PathBuf = std::env::temp_dir().join(format!(
+            "rigetti-pyo3-stubs-{test_name}-{}",
+            std::process::id()
+        ));
+        let _ = fs::remove_dir_all(&python_root);
+        fs::create_dir_all(&python_root).unwrap();
+
+        let module = Module {
+            doc: "A module for testing.".into(),
+            name: "mymod".into(),
+            default_module_name: "mymod".into(),
+            ..Module::default()
+        };
+
+        StubInfo {
+            modules: [("mymod".to_string(), module)].into(),
+            python_root,
+            is_mixed_layout: false,
+            config: StubGenConfig::default(),
+            pyproject_dir: None,
+            default_module_name: "mymod".into(),
+            project_name: "mymod".into(),
+        }
+    }
+
+    #[test]
+    fn test_render_matches_generate() {
+        let stub = stub_info("render");
+        stub.generate().unwrap();
+
+        let files = render(&stub).unwrap();
+        assert_eq!(files.len(), 1);
+        for (path, expected) in files {
+            assert_eq!(path, stub.python_root.join("mymod.pyi"));
+            assert_eq!(fs::read_to_string(path).unwrap(), expected);
+        }
+
+        fs::remove_dir_all(&stub.python_root).unwrap();
+    }
+
+    #[test]
+    fn test_render_mixed_layout_in_memory() {
+        let python_root = std::env::temp_dir().join(format!(
+            "rigetti-pyo3-stubs-never-written-{}",
+            std::process::id()
+        ));
+        let module = |name: &str| Module {
+            doc: format!("The {name} module."),
+            name: name.into(),
+            default_module_name: "pkg".into(),
+            ..Module::default()
+        };
+        let mut pkg = module("pkg");
+        pkg.submodules.insert("sub".into());
+        let stub = StubInfo {
+            modules: [
+                ("pkg".to_string(), pkg),
+                ("pkg.sub".to_string(), module("pkg.sub")),
+            ]
+            .into(),
+            python_root: python_root.clone(),
+            is_mixed_layout: true,
+            config: StubGenConfig::default(),
+            pyproject_dir: None,
+            default_module_name: "pkg".into(),
+            project_name: "pkg".into(),
+        };
+
+        let files = render(&stub).unwrap();
+        assert_eq!(
+            files.keys().collect::<Vec<_>>(),
+            [
+                &python_root.join("pkg").join("__init__.pyi"),
+                &python_root.join("pkg").join("sub").join("__init__.pyi"),
+            ]
+        );
+        assert!(files.values().next().unwrap().contains("from . import sub"));
+        assert!(!python_root.exists());
+    }
+
+    #[test]
+    fn test_verify_reports_mismatches() {
+        let stub = stub_info("verify");
+        let path = stub.python_root.join("mymod.pyi");
+
+        let diff = verify(&stub).unwrap();
+        assert!(matches!(
+            diff.mismatches.as_slice(),
+            [StubMismatch::Missing { .. }]
+        ));
+        assert_eq!(diff.mismatches[0].path(), path);
+
+        stub.generate().unwrap();
+        assert!(verify(&stub).unwrap().is_empty());
+
+        let stale = fs::read_to_string(&path)
+            .unwrap()
+            .replace("A module for testing.", "An outdated docstring.");
+        fs::write(&path, stale).unwrap();
+
+        let diff = verify(&stub).unwrap();
+        assert!(matches!(
+            diff.mismatches.as_slice(),
+            [StubMismatch::Changed { .. }]
+        ));
+        let report = diff.to_string();
+        assert!(report.contains("-An outdated docstring."), "{report}");
+        assert!(report.contains("+A module for testing."), "{report}");
+
+        fs::remove_dir_all(&stub.python_root).unwrap();
+    }
+}
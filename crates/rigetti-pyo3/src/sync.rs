@@ -334,11 +334,18 @@ macro_rules! py_async {
 /// assert await do_thing_async() == "done"
 /// ```
 ///
-/// With the `opentelemetry` feature enabled, this macro ensures Opentelemetry contexts are propagated.
+/// Doc comments on the function become the docstring of both variants,
+/// in `__doc__` and in generated stubs.
+/// The docstring of the `_async` variant has a note appended
+/// explaining that it returns an awaitable, which is its whole docstring if there are no doc comments.
+///
+/// With the `opentelemetry` feature enabled, this macro ensures Opentelemetry contexts are propagated:
+/// the caller's Python context is extracted, as with `pyo3_opentelemetry::pypropagate`,
+/// and attached to the future in both the sync and async variants.
 #[macro_export]
 macro_rules! py_function_sync_async {
     (
-        $(#[$meta: meta])+
+        $(#[$($meta: tt)*])+
         $pub:vis async fn $name:ident($($(#[$arg_meta: meta])*$arg: ident : $kind: ty),* $(,)?)
         $(-> PyResult<$ret: ty>)? $body: block
     ) => {
@@ -347,18 +354,18 @@ macro_rules! py_function_sync_async {
             $body
         }
 
-        $(#[$meta])+
+        $(#[$($meta)*])+
         #[allow(clippy::too_many_arguments)]
         #[pyo3(name = $name "")]
         $pub fn [< py_ $name >](py: $crate::pyo3::Python<'_> $(, $(#[$arg_meta])*$arg: $kind)*) $(-> PyResult<$ret>)? {
-            let res = $crate::sync::add_context_if_otel([< $name _impl >]($($arg),*));
+            let res = $crate::sync::add_python_context_if_otel(py, [< $name _impl >]($($arg),*));
             $crate::sync::invoke_async_from_py_sync(py, res)
         }
         }
 
         $crate::py_function_sync_async! {
             @async_block {
-                $(#[$meta])+
+                $(#[$($meta)*])+
                 $pub async fn $name($($(#[$arg_meta])*$arg : $kind),*) $(-> PyResult<$ret>)? $body
             }
         }
@@ -366,13 +373,13 @@ macro_rules! py_function_sync_async {
 
     (
         @async_block {
-            $(#[$meta: meta])+
+            $(#[$($meta: tt)*])+
             $pub:vis async fn $name:ident($($(#[$arg_meta: meta])*$arg: ident : $kind: ty),* $(,)?) $body: block
         }
     ) => {
         $crate::py_function_sync_async! {
             @async_block {
-                $(#[$meta])+
+                $(#[$($meta)*])+
                 $pub async fn $name($($(#[$arg_meta])*$arg: $kind),*) -> () $body
             }
         };
@@ -380,24 +387,71 @@ macro_rules! py_function_sync_async {
 
     (
         @async_block {
-            $(#[$meta: meta])+
+            $(#[$($meta: tt)*])+
             $pub:vis async fn $name:ident($($(#[$arg_meta: meta])*$arg: ident : $kind: ty),* $(,)?)
             -> PyResult<$ret:ty> $body: block
         }
     ) => {
         $crate::paste! {
-        $(#[$meta])+
-        #[pyo3(name = $name "_async")]
-        #[allow(clippy::too_many_arguments)]
-        $pub fn [< py_ $name _async >](py: $crate::pyo3::Python<'_> $(, $(#[$arg_meta])*$arg: $kind)*)
-            -> ::pyo3::PyResult<$crate::sync::Awaitable<'_, $ret>>
-        {
-            let res = $crate::sync::add_context_if_otel([< $name _impl >]($($arg),*));
-            $crate::pyo3_async_runtimes::tokio::future_into_py(py, res)
-                .map($crate::sync::Awaitable::new)
+        $crate::py_function_sync_async! {
+            @async_note { $(#[$($meta)*])+ } { $(#[$($meta)*])+ } {
+                #[pyo3(name = $name "_async")]
+                #[allow(clippy::too_many_arguments)]
+                $pub fn [< py_ $name _async >](py: $crate::pyo3::Python<'_> $(, $(#[$arg_meta])*$arg: $kind)*)
+                    -> ::pyo3::PyResult<$crate::sync::Awaitable<'_, $ret>>
+                {
+                    let res = $crate::sync::add_python_context_if_otel(py, [< $name _impl >]($($arg),*));
+                    $crate::pyo3_async_runtimes::tokio::future_into_py(py, res)
+                        .map($crate::sync::Awaitable::new)
+                }
+            }
         }
         }
     };
+
+    // The note is separated from the function's own docs by a blank line, if it has any.
+    (@async_note { #[doc $($doc: tt)*] $($attrs: tt)* } { $($meta: tt)* } { $($item: tt)* }) => {
+        $($meta)*
+        #[doc = ""]
+        #[doc = " This is the async variant of the function of the same name without the `_async` suffix:"]
+        #[doc = " it returns an awaitable, which must be `await`ed to get the result."]
+        $($item)*
+    };
+    (@async_note { #[$($attr: tt)*] $($attrs: tt)* } $meta: tt $item: tt) => {
+        $crate::py_function_sync_async! { @async_note { $($attrs)* } $meta $item }
+    };
+    (@async_note {} { $($meta: tt)* } { $($item: tt)* }) => {
+        $($meta)*
+        #[doc = " This is the async variant of the function of the same name without the `_async` suffix:"]
+        #[doc = " it returns an awaitable, which must be `await`ed to get the result."]
+        $($item)*
+    };
+}
+
+/// Adds the caller's Python context, as the `opentelemetry` feature was enabled at build time.
+///
+/// If the context can't be extracted from Python, the current Rust context is added instead.
+/// The first such failure is reported with a Python warning, as with
+/// `#[pypropagate(on_context_extraction_failure = "warn_once")]`.
+#[cfg(feature = "opentelemetry")]
+pub fn add_python_context_if_otel<T>(
+    py: Python<'_>,
+    res: T,
+) -> qcs_dependencies_client::opentelemetry::trace::WithContext<T> {
+    use qcs_dependencies_client::opentelemetry::{Context, trace::FutureExt};
+    let context = pyo3_opentelemetry::otel_context_from_python(py).unwrap_or_else(|error| {
+        static WARNED: std::sync::Once = std::sync::Once::new();
+        WARNED.call_once(|| pyo3_opentelemetry::__warn_context_propagation_failure(&error));
+        Context::current()
+    });
+    res.with_context(context)
+}
+
+/// Acts as an identity function, as the `opentelemetry` feature was not enabled at build time.
+#[cfg(not(feature = "opentelemetry"))]
+#[inline]
+pub const fn add_python_context_if_otel<T>(_py: Python<'_>, res: T) -> T {
+    res
 }
 
 /// Adds a context, as the `opentelemetry` feature was enabled at build time.
@@ -415,3 +469,63 @@ pub fn add_context_if_otel<T>(
 pub const fn add_context_if_otel<T>(res: T) -> T {
     res
 }
+
+#[cfg(test)]
+mod tests {
+    use pyo3::prelude::*;
+
+    crate::py_function_sync_async! {
+        /// Shave a yak.
+        #[pyfunction]
+        async fn shave() -> PyResult<bool> {
+            Ok(true)
+        }
+    }
+
+    crate::py_function_sync_async! {
+        #[pyfunction]
+        async fn groom() -> PyResult<bool> {
+            Ok(true)
+        }
+    }
+
+    fn doc(function: &Bound<'_, PyAny>) -> Option<String> {
+        function.getattr("__doc__").unwrap().extract().unwrap()
+    }
+
+    #[test]
+    fn test_docstrings() {
+        const NOTE: &str = "This is the async variant of the function of the same name without the `_async` suffix:\nit returns an awaitable, which must be `await`ed to get the result.";
+
+        Python::initialize();
+        Python::attach(|py| {
+            let module = PyModule::new(py, "yak_shaving").unwrap();
+            module
+                .add_function(wrap_pyfunction!(py_shave, &module).unwrap())
+                .unwrap();
+            module
+                .add_function(wrap_pyfunction!(py_shave_async, &module).unwrap())
+                .unwrap();
+            module
+                .add_function(wrap_pyfunction!(py_groom, &module).unwrap())
+                .unwrap();
+            module
+                .add_function(wrap_pyfunction!(py_groom_async, &module).unwrap())
+                .unwrap();
+
+            assert_eq!(
+                doc(&module.getattr("shave").unwrap()).as_deref(),
+                Some("Shave a yak.")
+            );
+            assert_eq!(
+                doc(&module.getattr("shave_async").unwrap()),
+                Some(format!("Shave a yak.\n\n{NOTE}"))
+            );
+            assert_eq!(doc(&module.getattr("groom").unwrap()), None);
+            assert_eq!(
+                doc(&module.getattr("groom_async").unwrap()).as_deref(),
+                Some(NOTE)
+            );
+        });
+    }
+}
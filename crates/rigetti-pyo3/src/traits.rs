@@ -15,20 +15,50 @@
 //! Macros for implementing "dunder" methods based on traits.
 
 /// Implement `__repr__` for a type that implements [`Debug`](std::fmt::Debug).
+///
+/// Each type may be preceded by doc comments, which replace the default docstring of `__repr__`.
+///
+/// ```
+/// use rigetti_pyo3::{impl_repr, pyo3::pyclass};
+///
+/// #[derive(Debug)]
+/// #[pyclass]
+/// struct Yak;
+///
+/// #[derive(Debug)]
+/// #[pyclass]
+/// struct Shears;
+///
+/// impl_repr!(
+///     Yak,
+///     /// Describe the shears, including how sharp they are.
+///     Shears,
+/// );
+/// ```
 #[macro_export]
 macro_rules! impl_repr {
-    ($($name:ident),* $(,)?) => {
-        $(
-            $crate::maybe_add_cfg_stubs_gen_stub_pymethods! {
-                #[$crate::pyo3::pymethods]
-                impl $name {
-                    /// Implements `__repr__` for Python in terms of the Rust
-                    /// [`Debug`](std::fmt::Debug) implementation.
-                    pub fn __repr__(&self) -> String {
-                        format!("{self:?}")
-                    }
+    (@impl [] $name:ident) => {
+        $crate::impl_repr!(
+            @impl [
+                " Implements `__repr__` for Python in terms of the Rust",
+                " [`Debug`](std::fmt::Debug) implementation."
+            ] $name
+        );
+    };
+    (@impl [$($doc:literal),+] $name:ident) => {
+        $crate::maybe_add_cfg_stubs_gen_stub_pymethods! {
+            #[$crate::pyo3::pymethods]
+            impl $name {
+                $(#[doc = $doc])+
+                pub fn __repr__(&self) -> String {
+                    format!("{self:?}")
                 }
             }
+        }
+    };
+    ($($(#[doc = $doc:literal])* $name:ident),* $(,)?) => {
+        $(
+            $crate::impl_repr!(@impl [$($doc),*] $name);
         )*
     };
 }
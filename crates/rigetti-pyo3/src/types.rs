@@ -0,0 +1,420 @@
+//! Wrappers around Python objects and ecosystem types that carry precise stub types.
+//!
+//! PyO3's handle types erase what a Python object contains, so [`pyo3_stub_gen`]
+//! has to describe a `Bound<'py, PyDict>` as a bare `dict` and a `Py<PyAny>` as `typing.Any`.
+//! The wrappers in this module keep the intended Python type as a type parameter,
+//! like [`Awaitable`](crate::sync::Awaitable) does for coroutines,
+//! and convert to and from Python exactly like the handle they wrap.
+//! Their [`PyStubType`] implementations are only available with the `stubs` feature,
+//! but the wrappers themselves can be used unconditionally.
+//!
+//! Many common types already have precise stubs in [`pyo3_stub_gen`], and don't need a wrapper:
+//!
+//! | Rust type                                   | Python type                                    |
+//! |---------------------------------------------|------------------------------------------------|
+//! | `std::path::PathBuf`                        | `pathlib.Path` (accepts `str \| os.PathLike`)  |
+//! | `chrono` dates, times, durations            | `datetime.date`, `datetime.datetime`, etc.     |
+//! | `numpy::PyArray<T, D>` and its borrows      | `numpy.typing.NDArray[numpy.<dtype>]`          |
+//! | `rust_decimal::Decimal`                     | `decimal.Decimal` (with the `rust_decimal` feature) |
+//!
+//! Types without a stub in [`pyo3_stub_gen`] need a local wrapper,
+//! since neither crate owns both the trait and the type:
+//!
+//! | Rust type                        | Wrapper           | Python type      | Feature |
+//! |----------------------------------|-------------------|------------------|---------|
+//! | `Bound<'py, PyDict>`             | [`PyDictOf`]      | `dict[K, V]`     |         |
+//! | `Bound<'py, PyList>`             | [`PyListOf`]      | `list[T]`        |         |
+//! | `Py<PyAny>`                      | [`PyObjectOf`]    | `T`              |         |
+//! | `uuid::Uuid`                     | `PyUuid`          | `uuid.UUID`      | `uuid`  |
+//! | `time::Date`                     | `PyDate`          | `datetime.date`  | `time`  |
+//! | `time::Time`                     | `PyTime`          | `datetime.time`  | `time`  |
+//! | `time::PrimitiveDateTime`        | `PyPrimitiveDateTime` | `datetime.datetime` | `time` |
+//! | `time::OffsetDateTime`           | `PyOffsetDateTime` | `datetime.datetime` | `time` |
+//! | `time::Duration`                 | `PyDuration`      | `datetime.timedelta` | `time` |
+//!
+//! The element types of [`PyDictOf`] and [`PyListOf`] only describe the stub;
+//! their contents aren't checked on extraction, so extract items as usual when using them.
+//!
+//! # Example
+//!
+//! ```
+//! use pyo3::prelude::*;
+//! use pyo3::types::PyDict;
+//! use rigetti_pyo3::types::PyDictOf;
+//!
+//! /// Appears in stubs as `def counts(names: dict[str, int]) -> dict[str, int]`.
+//! #[pyfunction]
+//! fn counts<'py>(names: PyDictOf<'py, String, u64>) -> PyDictOf<'py, String, u64> {
+//!     names
+//! }
+//! # fn main() {
+//! # Python::initialize();
+//! # Python::attach(|py| {
+//! #     let dict = PyDict::new(py);
+//! #     dict.set_item("yak", 1).unwrap();
+//! #     let result = counts(dict.as_any().extract().unwrap());
+//! #     assert_eq!(result.0.len(), 1);
+//! # });
+//! # }
+//! ```
+
+use std::marker::PhantomData;
+
+use pyo3::{
+    prelude::*,
+    types::{PyDict, PyList},
+};
+#[cfg(feature = "stubs")]
+use pyo3_stub_gen::{PyStubType, TypeInfo};
+
+/// Implements conversions for a wrapper around a `Bound<'py, $pytype>` with phantom type parameters.
+macro_rules! typed_bound_conversions {
+    ($wrapper:ident<'py $(, $param:ident)+>($pytype:ty)) => {
+        impl<'py, $($param),+> $wrapper<'py, $($param),+> {
+            /// Wrap a Python object, without checking the types of its contents.
+            #[must_use]
+            pub const fn new(obj: Bound<'py, $pytype>) -> Self {
+                Self(obj, PhantomData)
+            }
+        }
+
+        // Implemented by hand, since deriving would require the phantom parameters to implement
+        // these traits as well.
+        impl<$($param),+> Clone for $wrapper<'_, $($param),+> {
+            fn clone(&self) -> Self {
+                Self::new(self.0.clone())
+            }
+        }
+
+        impl<$($param),+> std::fmt::Debug for $wrapper<'_, $($param),+> {
+            fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+                f.debug_tuple(stringify!($wrapper)).field(&self.0).finish()
+            }
+        }
+
+        impl<'py, $($param),+> FromPyObject<'_, 'py> for $wrapper<'py, $($param),+> {
+            type Error = PyErr;
+
+            fn extract(obj: Borrowed<'_, 'py, PyAny>) -> Result<Self, Self::Error> {
+                Ok(Self::new(obj.cast::<$pytype>()?.to_owned()))
+            }
+        }
+
+        impl<'py, $($param),+> IntoPyObject<'py> for $wrapper<'py, $($param),+> {
+            type Target = $pytype;
+            type Output = Bound<'py, Self::Target>;
+            type Error = std::convert::Infallible;
+
+            fn into_pyobject(self, _: Python<'py>) -> Result<Self::Output, Self::Error> {
+                Ok(self.0)
+            }
+        }
+
+        impl<'a, 'py, $($param),+> IntoPyObject<'py> for &'a $wrapper<'py, $($param),+> {
+            type Target = $pytype;
+            type Output = Borrowed<'a, 'py, Self::Target>;
+            type Error = std::convert::Infallible;
+
+            fn into_pyobject(self, _: Python<'py>) -> Result<Self::Output, Self::Error> {
+                Ok(self.0.as_borrowed())
+            }
+        }
+
+        impl<'py, $($param),+> From<Bound<'py, $pytype>> for $wrapper<'py, $($param),+> {
+            fn from(obj: Bound<'py, $pytype>) -> Self {
+                Self::new(obj)
+            }
+        }
+    };
+}
+
+/// A Python `dict` that is stubbed as `dict[K, V]` rather than a bare `dict`.
+pub struct PyDictOf<'py, K, V>(pub Bound<'py, PyDict>, PhantomData<(K, V)>);
+
+typed_bound_conversions!(PyDictOf<'py, K, V>(PyDict));
+
+#[cfg(feature = "stubs")]
+impl<K: PyStubType, V: PyStubType> PyStubType for PyDictOf<'_, K, V> {
+    fn type_output() -> TypeInfo {
+        TypeInfo::dict_of::<K, V>()
+    }
+}
+
+/// A Python `list` that is stubbed as `list[T]` rather than a bare `list`.
+pub struct PyListOf<'py, T>(pub Bound<'py, PyList>, PhantomData<T>);
+
+typed_bound_conversions!(PyListOf<'py, T>(PyList));
+
+#[cfg(feature = "stubs")]
+impl<T: PyStubType> PyStubType for PyListOf<'_, T> {
+    fn type_output() -> TypeInfo {
+        TypeInfo::list_of::<T>()
+    }
+}
+
+/// An owned reference to a Python object that is stubbed as `T` rather than `typing.Any`.
+///
+/// Unlike `Bound`, this isn't tied to holding the GIL, so it can be stored in a `#[pyclass]`.
+/// `T` is only used for the stub; it's commonly another `#[pyclass]`,
+/// or a wrapper such as [`PyDictOf`] with an erased lifetime.
+pub struct PyObjectOf<T>(pub Py<PyAny>, PhantomData<fn() -> T>);
+
+impl<T> std::fmt::Debug for PyObjectOf<T> {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_tuple("PyObjectOf").field(&self.0).finish()
+    }
+}
+
+impl<T> PyObjectOf<T> {
+    /// Wrap a Python object, without checking its type.
+    #[must_use]
+    pub const fn new(obj: Py<PyAny>) -> Self {
+        Self(obj, PhantomData)
+    }
+
+    /// Clone the reference to the underlying Python object.
+    #[must_use]
+    pub fn clone_ref(&self, py: Python<'_>) -> Self {
+        Self::new(self.0.clone_ref(py))
+    }
+}
+
+impl<'py, T> FromPyObject<'_, 'py> for PyObjectOf<T> {
+    type Error = PyErr;
+
+    fn extract(obj: Borrowed<'_, 'py, PyAny>) -> Result<Self, Self::Error> {
+        Ok(Self::new(obj.to_owned().unbind()))
+    }
+}
+
+impl<'py, T> IntoPyObject<'py> for PyObjectOf<T> {
+    type Target = PyAny;
+    type Output = Bound<'py, Self::Target>;
+    type Error = std::convert::Infallible;
+
+    fn into_pyobject(self, py: Python<'py>) -> Result<Self::Output, Self::Error> {
+        Ok(self.0.into_bound(py))
+    }
+}
+
+impl<'a, 'py, T> IntoPyObject<'py> for &'a PyObjectOf<T> {
+    type Target = PyAny;
+    type Output = Borrowed<'a, 'py, Self::Target>;
+    type Error = std::convert::Infallible;
+
+    fn into_pyobject(self, py: Python<'py>) -> Result<Self::Output, Self::Error> {
+        Ok(self.0.bind_borrowed(py))
+    }
+}
+
+impl<T> From<Py<PyAny>> for PyObjectOf<T> {
+    fn from(obj: Py<PyAny>) -> Self {
+        Self::new(obj)
+    }
+}
+
+#[cfg(feature = "stubs")]
+impl<T: PyStubType> PyStubType for PyObjectOf<T> {
+    fn type_output() -> TypeInfo {
+        T::type_output()
+    }
+
+    fn type_input() -> TypeInfo {
+        T::type_input()
+    }
+}
+
+/// Implements a wrapper around an ecosystem type that converts exactly like it,
+/// with a stub of the given Python type, when the given feature is enabled.
+macro_rules! ecosystem_wrapper {
+    (
+        $(#[$meta:meta])*
+        $feature:literal, $wrapper:ident($inner:ty) => $python:literal in $module:literal
+    ) => {
+        $(#[$meta])*
+        #[cfg(feature = $feature)]
+        #[derive(Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord, Hash)]
+        pub struct $wrapper(pub $inner);
+
+        #[cfg(feature = $feature)]
+        impl From<$inner> for $wrapper {
+            fn from(value: $inner) -> Self {
+                Self(value)
+            }
+        }
+
+        #[cfg(feature = $feature)]
+        impl From<$wrapper> for $inner {
+            fn from(value: $wrapper) -> Self {
+                value.0
+            }
+        }
+
+        #[cfg(feature = $feature)]
+        impl<'py> FromPyObject<'_, 'py> for $wrapper {
+            type Error = PyErr;
+
+            fn extract(obj: Borrowed<'_, 'py, PyAny>) -> Result<Self, Self::Error> {
+                obj.extract().map(Self)
+            }
+        }
+
+        #[cfg(feature = $feature)]
+        impl<'py> IntoPyObject<'py> for $wrapper {
+            type Target = <$inner as IntoPyObject<'py>>::Target;
+            type Output = <$inner as IntoPyObject<'py>>::Output;
+            type Error = <$inner as IntoPyObject<'py>>::Error;
+
+            fn into_pyobject(self, py: Python<'py>) -> Result<Self::Output, Self::Error> {
+                self.0.into_pyobject(py)
+            }
+        }
+
+        #[cfg(feature = $feature)]
+        impl<'py> IntoPyObject<'py> for &$wrapper {
+            type Target = <$inner as IntoPyObject<'py>>::Target;
+            type Output = <$inner as IntoPyObject<'py>>::Output;
+            type Error = <$inner as IntoPyObject<'py>>::Error;
+
+            fn into_pyobject(self, py: Python<'py>) -> Result<Self::Output, Self::Error> {
+                self.0.into_pyobject(py)
+            }
+        }
+
+        #[cfg(all(feature = $feature, feature = "stubs"))]
+        impl PyStubType for $wrapper {
+            fn type_output() -> TypeInfo {
+                TypeInfo::with_module($python, $module.into())
+            }
+        }
+    };
+}
+
+ecosystem_wrapper! {
+    /// A [`uuid::Uuid`] that converts to and from a Python `uuid.UUID`.
+    #[cfg_attr(feature = "uuid", derive(Default))]
+    "uuid", PyUuid(uuid::Uuid) => "uuid.UUID" in "uuid"
+}
+
+ecosystem_wrapper! {
+    /// A [`time::Date`] that converts to and from a Python `datetime.date`.
+    "time", PyDate(time::Date) => "datetime.date" in "datetime"
+}
+
+ecosystem_wrapper! {
+    /// A [`time::Time`] that converts to and from a naive Python `datetime.time`.
+    "time", PyTime(time::Time) => "datetime.time" in "datetime"
+}
+
+ecosystem_wrapper! {
+    /// A [`time::PrimitiveDateTime`] that converts to and from a naive Python `datetime.datetime`.
+    "time", PyPrimitiveDateTime(time::PrimitiveDateTime) => "datetime.datetime" in "datetime"
+}
+
+ecosystem_wrapper! {
+    /// A [`time::OffsetDateTime`] that converts to and from an aware Python `datetime.datetime`.
+    "time", PyOffsetDateTime(time::OffsetDateTime) => "datetime.datetime" in "datetime"
+}
+
+ecosystem_wrapper! {
+    /// A [`time::Duration`] that converts to and from a Python `datetime.timedelta`.
+    #[cfg_attr(feature = "time", derive(Default))]
+    "time", PyDuration(time::Duration) => "datetime.timedelta" in "datetime"
+}
+
+#[cfg(all(test, feature = "stubs"))]
+mod tests {
+    use pyo3::types::PyAny;
+    use pyo3_stub_gen::PyStubType;
+
+    use super::{PyDictOf, PyListOf, PyObjectOf};
+
+    #[test]
+    fn test_stub_types() {
+        assert_eq!(
+            PyDictOf::<'_, String, i64>::type_output().name,
+            "builtins.dict[builtins.str, builtins.int]"
+        );
+        assert_eq!(
+            PyListOf::<'_, f64>::type_output().name,
+            "builtins.list[builtins.float]"
+        );
+        assert_eq!(
+            PyObjectOf::<PyListOf<'static, bool>>::type_output().name,
+            "builtins.list[builtins.bool]"
+        );
+        assert_eq!(PyObjectOf::<PyAny>::type_output().name, "typing.Any");
+    }
+
+    #[test]
+    fn test_clone_and_debug_without_bounds_on_params() {
+        use pyo3::{
+            prelude::*,
+            types::{PyDict, PyList},
+        };
+
+        /// Neither `Clone` nor `Debug`, so only the Python object can need them.
+        struct Opaque;
+
+        Python::initialize();
+        Python::attach(|py| {
+            let dict = PyDictOf::<'_, Opaque, Opaque>::new(PyDict::new(py));
+            assert!(dict.clone().0.is(&dict.0));
+            assert_eq!(format!("{dict:?}"), "PyDictOf({})");
+
+            let list = PyListOf::<'_, Opaque>::new(PyList::empty(py));
+            assert!(list.clone().0.is(&list.0));
+            assert_eq!(format!("{list:?}"), "PyListOf([])");
+
+            let object = PyObjectOf::<Opaque>::new(py.None());
+            assert!(format!("{object:?}").starts_with("PyObjectOf(Py("));
+        });
+    }
+
+    #[cfg(feature = "uuid")]
+    #[test]
+    fn test_uuid_stub_type() {
+        assert_eq!(super::PyUuid::type_output().name, "uuid.UUID");
+    }
+
+    #[cfg(feature = "time")]
+    #[test]
+    fn test_time_stub_types() {
+        use super::{PyDate, PyDuration, PyOffsetDateTime, PyPrimitiveDateTime, PyTime};
+
+        assert_eq!(PyDate::type_output().name, "datetime.date");
+        assert_eq!(PyTime::type_output().name, "datetime.time");
+        assert_eq!(PyPrimitiveDateTime::type_output().name, "datetime.datetime");
+        assert_eq!(PyOffsetDateTime::type_output().name, "datetime.datetime");
+        assert_eq!(PyDuration::type_output().name, "datetime.timedelta");
+    }
+
+    #[cfg(feature = "time")]
+    #[test]
+    fn test_time_roundtrip() {
+        use pyo3::prelude::*;
+        use time::{Date, Month, Time, UtcOffset};
+
+        use super::{PyDate, PyDuration, PyOffsetDateTime};
+
+        Python::initialize();
+        Python::attach(|py| {
+            let date = Date::from_calendar_date(2024, Month::February, 29).unwrap();
+            let object = PyDate(date).into_pyobject(py).unwrap();
+            assert_eq!(object.get_type().name().unwrap(), "date");
+            assert_eq!(object.extract::<PyDate>().unwrap(), PyDate(date));
+
+            let moment = PyOffsetDateTime(
+                date.with_time(Time::from_hms(12, 30, 0).unwrap())
+                    .assume_offset(UtcOffset::from_hms(1, 0, 0).unwrap()),
+            );
+            let object = (&moment).into_pyobject(py).unwrap();
+            assert_eq!(object.extract::<PyOffsetDateTime>().unwrap(), moment);
+
+            let duration = PyDuration(time::Duration::minutes(90));
+            let object = duration.into_pyobject(py).unwrap();
+            assert_eq!(object.get_type().name().unwrap(), "timedelta");
+            assert_eq!(object.extract::<PyDuration>().unwrap(), duration);
+        });
+    }
+}
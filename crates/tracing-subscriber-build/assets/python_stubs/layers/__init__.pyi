@@ -14,17 +14,19 @@ from __future__ import annotations
 from typing import TYPE_CHECKING
 
 from . import file as file
+from . import python_logging as python_logging
 {{#if layer_otel_otlp_file }}from . import otel_otlp_file as otel_otlp_file{{/if}}
 {{#if layer_otel_otlp}}from . import otel_otlp as otel_otlp{{/if}}
 
 if TYPE_CHECKING:
-  {{#if any_additional_layer}}from typing import Union{{/if}}
+  from typing import Union
 
-  {{#if any_additional_layer}}Config = Union[
+  Config = Union[
     file.Config,
+    python_logging.Config,
     {{#if layer_otel_otlp_file }}otel_otlp_file.Config,{{/if}}
     {{#if layer_otel_otlp }}otel_otlp.Config,{{/if}}
-      ]{{else}}Config = file.Config{{/if}}
+      ]
   """
   One of the supported layer configurations that may be set on the subscriber configuration.
   """
@@ -48,7 +48,6 @@ struct Data {
     version: String,
     layer_otel_otlp_file: bool,
     layer_otel_otlp: bool,
-    any_additional_layer: bool,
 }
 
 impl Data {
@@ -59,8 +58,6 @@ impl Data {
             version: env!("CARGO_PKG_VERSION").to_string(),
             layer_otel_otlp_file: cfg!(feature = "layer-otel-otlp-file"),
             layer_otel_otlp: cfg!(feature = "layer-otel-otlp"),
-            any_additional_layer: cfg!(feature = "layer-otel-otlp-file")
-                || cfg!(feature = "layer-otel-otlp"),
         }
     }
 }
@@ -137,8 +134,10 @@ pub fn write_stub_files(
     .map_err(Error::from)?;
     include_stub_and_init!(directory, "common/", hb);
     include_stub_and_init!(directory, "subscriber/", hb);
+    include_stub_and_init!(directory, "logging/", hb);
     include_stub_and_init!(directory, "layers/", hb);
     include_stub_and_init!(directory, "layers/file/", hb);
+    include_stub_and_init!(directory, "layers/python_logging/", hb);
     #[cfg(feature = "layer-otel-otlp-file")]
     include_stub_and_init!(directory, "layers/otel_otlp_file/", hb);
     #[cfg(feature = "layer-otel-otlp")]
@@ -130,6 +130,7 @@ impl Tracing {
 
     fn __exit__<'py>(
         &mut self,
+        py: Python<'py>,
         _exc_type: Option<Bound<'py, PyAny>>,
         _exc_value: Option<Bound<'py, PyAny>>,
         _traceback: Option<Bound<'py, PyAny>>,
@@ -141,7 +142,10 @@ impl Tracing {
             // method returns a Tokio runtime, which cannot be dropped within another
             // runtime. Additionally, `pyo3_async_runtimes::tokio::future_into_py` futures
             // must resolve to something that implements `IntoPyObject`.
-            if let Some(export_runtime) = py_rt.block_on(export_process.shutdown())? {
+            //
+            // The GIL is released while blocking, as layers may need to acquire it in order to
+            // flush (e.g. the Python logging layer).
+            if let Some(export_runtime) = py.detach(|| py_rt.block_on(export_process.shutdown()))? {
                 // This immediately shuts the runtime down. The expectation here is that the
                 // process shutdown is responsible for cleaning up all background tasks and
                 // shutting down gracefully.
@@ -161,7 +165,7 @@ impl Tracing {
         exc_value: Option<Bound<'py, PyAny>>,
         traceback: Option<Bound<'py, PyAny>>,
     ) -> PyResult<Awaitable<'py, PyNone>> {
-        self.__exit__(exc_type, exc_value, traceback)?;
+        self.__exit__(py, exc_type, exc_value, traceback)?;
         pyo3_async_runtimes::tokio::future_into_py(py, async { Ok(()) }).map(Into::into)
     }
 }
@@ -204,7 +208,9 @@ mod test {
             filter: Some("error,pyo3_tracing_subscriber=info".to_string()),
             instrumentation_library: None,
         });
-        let subscriber = Box::new(TracingSubscriberRegistryConfig { layer_config });
+        let subscriber = Box::new(TracingSubscriberRegistryConfig {
+            layer_configs: vec![layer_config],
+        });
         let config = TracingConfig::Global(GlobalTracingConfig {
             export_process: ExportProcessConfig::Simple(SimpleConfig {
                 subscriber: crate::subscriber::PyConfig {
@@ -282,7 +288,9 @@ mod test {
             filter: Some("error,pyo3_tracing_subscriber=info".to_string()),
             instrumentation_library: None,
         });
-        let subscriber = Box::new(TracingSubscriberRegistryConfig { layer_config });
+        let subscriber = Box::new(TracingSubscriberRegistryConfig {
+            layer_configs: vec![layer_config],
+        });
         let config = TracingConfig::CurrentThread(CurrentThreadTracingConfig {
             export_process: ExportProcessConfig::Simple(SimpleConfig {
                 subscriber: crate::subscriber::PyConfig {
@@ -339,4 +347,69 @@ mod test {
             );
         }
     }
+
+    #[test]
+    /// Test that a current thread simple export process exports to each of multiple configured
+    /// layers and shuts all of them down.
+    fn test_current_thread_multiple_layers() {
+        let otlp_file_path = get_tempfile("test_current_thread_multiple_layers_otlp");
+        let fmt_file_path = get_tempfile("test_current_thread_multiple_layers_fmt");
+        let subscriber = Box::new(TracingSubscriberRegistryConfig {
+            layer_configs: vec![
+                Box::new(crate::layers::otel_otlp_file::Config {
+                    file_path: Some(otlp_file_path.as_os_str().to_str().unwrap().to_owned()),
+                    filter: Some("error,pyo3_tracing_subscriber=info".to_string()),
+                    instrumentation_library: None,
+                }),
+                Box::new(crate::layers::fmt_file::Config {
+                    file_path: Some(fmt_file_path.as_os_str().to_str().unwrap().to_owned()),
+                    pretty: false,
+                    filter: Some("error,pyo3_tracing_subscriber=info".to_string()),
+                    json: true,
+                }),
+            ],
+        });
+        let config = TracingConfig::CurrentThread(CurrentThreadTracingConfig {
+            export_process: ExportProcessConfig::Simple(SimpleConfig {
+                subscriber: crate::subscriber::PyConfig {
+                    subscriber_config: subscriber,
+                },
+            }),
+        });
+        let export_process = ExportProcess::start(config).unwrap();
+
+        for _ in 0..N_SPANS {
+            example();
+            tracing::info!("example finished");
+        }
+
+        let rt2 = Builder::new_current_thread().enable_time().build().unwrap();
+        let _guard = rt2.enter();
+        let runtime = rt2
+            .block_on(tokio::time::timeout(Duration::from_secs(1), async move {
+                export_process.shutdown().await
+            }))
+            .unwrap()
+            .unwrap();
+        assert!(runtime.is_none());
+
+        let reader = std::io::BufReader::new(std::fs::File::open(otlp_file_path).unwrap());
+        let span_count = reader
+            .lines()
+            .map(|line| {
+                let span_data: otlp::TracesData =
+                    serde_json::from_str(line.unwrap().as_str()).unwrap();
+                span_data
+                    .resource_spans
+                    .into_iter()
+                    .flat_map(|resource_span| resource_span.scope_spans)
+                    .map(|scope_span| scope_span.spans.len())
+                    .sum::<usize>()
+            })
+            .sum::<usize>();
+        assert_eq!(span_count, N_SPANS);
+
+        let reader = std::io::BufReader::new(std::fs::File::open(fmt_file_path).unwrap());
+        assert_eq!(reader.lines().count(), N_SPANS);
+    }
 }
@@ -17,27 +17,38 @@
 //!
 //! Currently, the following layers are supported:
 //!
-//! * [`crate::layers::fmt_file::Config`] - a layer which writes spans to a file (or stdout) in
-//! * [`crate::layers::otel_otlp_file::Config`] - a layer which writes spans to a file (or stdout) in
+//! * `fmt_file::Config` - a layer which writes spans to a file (or stdout) in a human readable format.
+//! * `otel_otlp_file::Config` - a layer which writes spans to a file (or stdout) in
 //!   the `OpenTelemetry` OTLP JSON-serialized format.
-//! * [`crate::layers::otel_otlp::Config`] - a layer which exports spans to an `OpenTelemetry` collector.
+//! * `otel_otlp::Config` - a layer which exports spans to an `OpenTelemetry` collector.
+//! * `python_logging::Config` - a layer which forwards events to a Python `logging.Logger`.
+//!
+//! Downstream libraries may add their own layers by implementing [`Config`] for a `#[pyclass]`
+//! and registering it with [`register_config`].
 pub(crate) mod fmt_file;
 #[cfg(feature = "layer-otel-otlp")]
 pub(crate) mod otel_otlp;
 #[cfg(feature = "layer-otel-otlp-file")]
 pub(crate) mod otel_otlp_file;
+pub(crate) mod python_logging;
 
 use qcs_dependencies_client::opentelemetry_sdk;
 
-use std::fmt::Debug;
+use std::{
+    any::TypeId,
+    fmt::Debug,
+    sync::{PoisonError, RwLock},
+};
 
-use pyo3::prelude::*;
+use pyo3::{PyClass, exceptions::PyTypeError, prelude::*};
 use tracing_subscriber::{
     EnvFilter, Layer, Registry,
     filter::{FromEnvError, ParseError},
 };
 
-pub(super) type Shutdown = Box<
+/// A function which shuts down a layer, flushing any buffered trace data. This is invoked when the
+/// `Tracing` context manager exits.
+pub type Shutdown = Box<
     dyn (FnOnce() -> std::pin::Pin<Box<dyn Future<Output = ShutdownResult<()>> + Send + Sync>>)
         + Send
         + Sync,
@@ -45,9 +56,11 @@ pub(super) type Shutdown = Box<
 
 /// Carries the built tracing subscriber layer and a shutdown function that can later be used to
 /// shutdown the subscriber upon context manager exit.
-pub(crate) struct WithShutdown {
-    pub(crate) layer: Box<dyn Layer<Registry> + Send + Sync>,
-    pub(crate) shutdown: Shutdown,
+pub struct WithShutdown {
+    /// The layer to add to the [`tracing_subscriber::Registry`].
+    pub layer: Box<dyn Layer<Registry> + Send + Sync>,
+    /// The function to call when the subscriber is shut down.
+    pub shutdown: Shutdown,
 }
 
 impl Debug for WithShutdown {
@@ -59,8 +72,29 @@ impl Debug for WithShutdown {
     }
 }
 
+/// An error that occurs while building a layer from its [`Config`].
+#[derive(thiserror::Error, Debug)]
+#[error(transparent)]
+pub struct BuildError(BuildErrorKind);
+
+impl BuildError {
+    /// Wraps an error raised while building a custom layer.
+    pub fn custom(error: impl Into<Box<dyn std::error::Error + Send + Sync>>) -> Self {
+        Self(BuildErrorKind::Custom(error.into()))
+    }
+}
+
+impl<E> From<E> for BuildError
+where
+    BuildErrorKind: From<E>,
+{
+    fn from(error: E) -> Self {
+        Self(BuildErrorKind::from(error))
+    }
+}
+
 #[derive(thiserror::Error, Debug)]
-pub(crate) enum BuildError {
+enum BuildErrorKind {
     #[cfg(feature = "layer-otel-otlp-file")]
     #[error("file layer: {0}")]
     File(#[from] otel_otlp_file::BuildError),
@@ -69,31 +103,72 @@ pub(crate) enum BuildError {
     Otlp(#[from] otel_otlp::BuildError),
     #[error("fmt layer: {0}")]
     FmtFile(#[from] fmt_file::BuildError),
+    #[error("python logging layer: {0}")]
+    PythonLogging(#[from] python_logging::BuildError),
     #[error("failed to parse specified trace filter: {0}")]
     TraceFilterParseError(#[from] ParseError),
     #[error("failed to parse trace filter from RUST_LOG: {0}")]
     TraceFilterEnvError(#[from] FromEnvError),
+    #[error("custom layer: {0}")]
+    Custom(Box<dyn std::error::Error + Send + Sync>),
 }
 
+/// An error that occurs while shutting down a layer.
 #[derive(thiserror::Error, Debug)]
-#[non_exhaustive]
-pub(crate) enum ShutdownError {
-    // This will eventually accept a `CustomError` that can be set by upstream libraries.
-    // See https://github.com/rigetti/pyo3-opentelemetry/issues/4
+#[error(transparent)]
+pub struct ShutdownError(ShutdownErrorKind);
+
+impl ShutdownError {
+    /// Wraps an error raised while shutting down a custom layer.
+    pub fn custom(error: impl Into<Box<dyn std::error::Error + Send + Sync>>) -> Self {
+        Self(ShutdownErrorKind::Custom(error.into()))
+    }
+}
+
+impl<E> From<E> for ShutdownError
+where
+    ShutdownErrorKind: From<E>,
+{
+    fn from(error: E) -> Self {
+        Self(ShutdownErrorKind::from(error))
+    }
+}
+
+#[derive(thiserror::Error, Debug)]
+enum ShutdownErrorKind {
     #[error("subscriber shutdown failed: {0}")]
     OTel(#[from] opentelemetry_sdk::error::OTelSdkError),
+    #[error("custom layer shutdown failed: {0}")]
+    Custom(Box<dyn std::error::Error + Send + Sync>),
 }
 
-pub(crate) type ShutdownResult<T> = Result<T, ShutdownError>;
+/// The result of shutting down a layer.
+pub type ShutdownResult<T> = Result<T, ShutdownError>;
 
-pub(super) type LayerBuildResult<T> = Result<T, BuildError>;
+/// The result of building a layer.
+pub type LayerBuildResult<T> = Result<T, BuildError>;
 
-pub(crate) trait Config: Send + Sync + BoxDynConfigClone + Debug {
+/// A configuration from which a tracing subscriber layer can be built.
+///
+/// The layers provided by this crate implement this trait. Downstream libraries may implement it
+/// for their own `#[pyclass]` types and make them available to Python with [`register_config`].
+pub trait Config: Send + Sync + BoxDynConfigClone + Debug {
+    /// Builds the layer along with the function used to shut it down.
+    ///
+    /// # Errors
+    ///
+    /// Returns a [`BuildError`] if the layer cannot be built. Use [`BuildError::custom`] to
+    /// wrap errors specific to the implementing layer.
     fn build(&self, batch: bool) -> LayerBuildResult<WithShutdown>;
+
+    /// Whether the layer requires a Tokio runtime to export trace data, even when it is not
+    /// exporting in batch.
     fn requires_runtime(&self) -> bool;
 }
 
-pub(crate) trait BoxDynConfigClone {
+/// Clones a [`Config`] trait object. This is implemented for every [`Config`] that is [`Clone`].
+pub trait BoxDynConfigClone {
+    /// Clones `self` into a boxed [`Config`].
     fn clone_box(&self) -> Box<dyn Config>;
 }
 
@@ -114,6 +189,106 @@ impl Clone for Box<dyn Config> {
     }
 }
 
+/// Extracts a registered custom [`Config`] from a Python object, if the object is of the
+/// registered type.
+type ExtractCustomConfig = fn(&Bound<'_, PyAny>) -> Option<Box<dyn Config>>;
+
+/// The registered custom configurations, keyed by the [`TypeId`] of the registered type.
+static CUSTOM_CONFIGS: RwLock<Vec<(TypeId, ExtractCustomConfig)>> = RwLock::new(Vec::new());
+
+/// Registers a custom layer configuration so that instances of `T` are accepted wherever Python
+/// users may pass a layer configuration, such as `subscriber.Config(layer=...)`.
+///
+/// This should be called while initializing your Python module, alongside adding `T` to the
+/// module. Registration applies to the whole process; registering the same type more than once
+/// has no additional effect.
+///
+/// ```
+/// use pyo3::prelude::*;
+/// use pyo3_tracing_subscriber::layers::{Config, LayerBuildResult, WithShutdown};
+///
+/// #[pyclass(skip_from_py_object)]
+/// #[derive(Clone, Debug)]
+/// struct NoopConfig;
+///
+/// impl Config for NoopConfig {
+///     fn build(&self, _batch: bool) -> LayerBuildResult<WithShutdown> {
+///         Ok(WithShutdown {
+///             layer: Box::new(tracing_subscriber::layer::Identity::new()),
+///             shutdown: Box::new(|| Box::pin(async { Ok(()) })),
+///         })
+///     }
+///
+///     fn requires_runtime(&self) -> bool {
+///         false
+///     }
+/// }
+///
+/// #[pymodule]
+/// fn example(py: Python, m: &Bound<'_, PyModule>) -> PyResult<()> {
+///     m.add_class::<NoopConfig>()?;
+///     pyo3_tracing_subscriber::layers::register_config::<NoopConfig>();
+///     pyo3_tracing_subscriber::add_submodule("example", "tracing_subscriber", py, m)?;
+///     Ok(())
+/// }
+/// ```
+pub fn register_config<T>()
+where
+    T: Config + PyClass + Clone,
+{
+    fn extract<T>(object: &Bound<'_, PyAny>) -> Option<Box<dyn Config>>
+    where
+        T: Config + PyClass + Clone,
+    {
+        object
+            .cast::<T>()
+            .ok()
+            .map(|config| config.borrow().clone_box())
+    }
+
+    let mut configs = CUSTOM_CONFIGS
+        .write()
+        .unwrap_or_else(PoisonError::into_inner);
+    let type_id = TypeId::of::<T>();
+    if !configs.iter().any(|(registered, _)| *registered == type_id) {
+        configs.push((type_id, extract::<T>));
+    }
+}
+
+/// A layer configuration of a type registered with [`register_config`].
+#[derive(Clone, Debug)]
+pub(crate) struct CustomConfig(Box<dyn Config>);
+
+impl<'py> FromPyObject<'_, 'py> for CustomConfig {
+    type Error = PyErr;
+
+    fn extract(object: Borrowed<'_, 'py, PyAny>) -> PyResult<Self> {
+        let configs = CUSTOM_CONFIGS
+            .read()
+            .unwrap_or_else(PoisonError::into_inner);
+        configs
+            .iter()
+            .find_map(|(_, extract)| extract(&object))
+            .map(Self)
+            .ok_or_else(|| {
+                PyTypeError::new_err(format!(
+                    "{} is not a registered layer configuration",
+                    object.get_type()
+                ))
+            })
+    }
+}
+
+impl Config for CustomConfig {
+    fn build(&self, batch: bool) -> LayerBuildResult<WithShutdown> {
+        self.0.build(batch)
+    }
+
+    fn requires_runtime(&self) -> bool {
+        self.0.requires_runtime()
+    }
+}
+
 #[cfg(any(feature = "layer-otel-otlp", feature = "layer-otel-otlp-file"))]
 pub(super) fn force_flush_provider_as_shutdown(
     provider: opentelemetry_sdk::trace::SdkTracerProvider,
@@ -139,7 +314,13 @@ pub(super) fn force_flush_provider_as_shutdown(
 /// `env_filter` argument specified on any layer configuration.
 const PYO3_OPENTELEMETRY_ENV_FILTER: &str = "PYO3_OPENTELEMETRY_ENV_FILTER";
 
-pub(super) fn build_env_filter(env_filter: Option<String>) -> Result<EnvFilter, BuildError> {
+/// Builds the [`EnvFilter`] for a layer. An explicit `env_filter` takes precedence, followed by
+/// the `PYO3_OPENTELEMETRY_ENV_FILTER` and `RUST_LOG` environment variables.
+///
+/// # Errors
+///
+/// Returns a [`BuildError`] if the filter cannot be parsed.
+pub fn build_env_filter(env_filter: Option<String>) -> Result<EnvFilter, BuildError> {
     env_filter
         .or_else(|| std::env::var(PYO3_OPENTELEMETRY_ENV_FILTER).ok())
         .or_else(|| std::env::var(EnvFilter::DEFAULT_ENV).ok())
@@ -158,6 +339,8 @@ pub(crate) enum PyConfig {
     #[cfg(feature = "layer-otel-otlp")]
     Otlp(otel_otlp::PyConfig),
     File(fmt_file::Config),
+    PythonLogging(python_logging::Config),
+    Custom(CustomConfig),
 }
 
 impl Default for PyConfig {
@@ -174,6 +357,8 @@ impl Config for PyConfig {
             #[cfg(feature = "layer-otel-otlp")]
             Self::Otlp(config) => config.build(batch),
             Self::File(config) => config.build(batch),
+            Self::PythonLogging(config) => config.build(batch),
+            Self::Custom(config) => config.build(batch),
         }
     }
 
@@ -184,6 +369,8 @@ impl Config for PyConfig {
             #[cfg(feature = "layer-otel-otlp")]
             Self::Otlp(config) => config.requires_runtime(),
             Self::File(config) => config.requires_runtime(),
+            Self::PythonLogging(config) => config.requires_runtime(),
+            Self::Custom(config) => config.requires_runtime(),
         }
     }
 }
@@ -220,5 +407,89 @@ pub(crate) fn init_submodule<'py>(
     m.add_submodule(&submod)?;
     modules.set_item(qualified_name, submod)?;
 
+    let submod = PyModule::new(py, "python_logging")?;
+    let qualified_name = format!("{name}.python_logging");
+    python_logging::init_submodule(qualified_name.as_str(), py, &submod)?;
+    m.add_submodule(&submod)?;
+    modules.set_item(qualified_name, submod)?;
+
     Ok(())
 }
+
+#[cfg(test)]
+mod test {
+    use pyo3::{prelude::*, types::PyDict};
+
+    use super::{Config, LayerBuildResult, PyConfig, WithShutdown};
+
+    /// A custom layer configuration, distinguishable from the built-in layers because it
+    /// requires a runtime.
+    #[pyclass(skip_from_py_object)]
+    #[derive(Clone, Debug)]
+    struct CustomConfig;
+
+    impl Config for CustomConfig {
+        fn build(&self, _batch: bool) -> LayerBuildResult<WithShutdown> {
+            Ok(WithShutdown {
+                layer: Box::new(tracing_subscriber::layer::Identity::new()),
+                shutdown: Box::new(|| Box::pin(async { Ok(()) })),
+            })
+        }
+
+        fn requires_runtime(&self) -> bool {
+            true
+        }
+    }
+
+    /// An unregistered layer configuration.
+    #[pyclass(skip_from_py_object)]
+    #[derive(Clone, Debug)]
+    struct UnregisteredConfig;
+
+    impl Config for UnregisteredConfig {
+        fn build(&self, batch: bool) -> LayerBuildResult<WithShutdown> {
+            CustomConfig.build(batch)
+        }
+
+        fn requires_runtime(&self) -> bool {
+            true
+        }
+    }
+
+    #[test]
+    /// Test that a registered custom configuration can be passed as a subscriber layer.
+    fn test_register_config() {
+        Python::initialize();
+        super::register_config::<CustomConfig>();
+        super::register_config::<CustomConfig>();
+        assert_eq!(
+            super::CUSTOM_CONFIGS
+                .read()
+                .unwrap()
+                .iter()
+                .filter(|(type_id, _)| *type_id == std::any::TypeId::of::<CustomConfig>())
+                .count(),
+            1
+        );
+
+        Python::attach(|py| {
+            let custom = Bound::new(py, CustomConfig).unwrap();
+            let config: PyConfig = custom.extract().unwrap();
+            assert!(matches!(config, PyConfig::Custom(_)));
+            assert!(config.requires_runtime());
+
+            let kwargs = PyDict::new(py);
+            kwargs.set_item("layer", &custom).unwrap();
+            let subscriber = py
+                .get_type::<crate::subscriber::PyConfig>()
+                .call((), Some(&kwargs))
+                .unwrap()
+                .cast_into::<crate::subscriber::PyConfig>()
+                .unwrap();
+            assert!(subscriber.borrow().subscriber_config.requires_runtime());
+
+            let unregistered = Bound::new(py, UnregisteredConfig).unwrap();
+            assert!(unregistered.extract::<PyConfig>().is_err());
+        });
+    }
+}
@@ -0,0 +1,515 @@
+// Copyright 2023 Rigetti Computing
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+// http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+use std::{
+    cell::Cell,
+    sync::mpsc::{Receiver, Sender},
+};
+
+use crate::create_init_submodule;
+use pyo3::{IntoPyObjectExt, prelude::*, types::PyTuple};
+use tracing::{Event, Subscriber, field::Field, span};
+use tracing_log::NormalizeEvent;
+use tracing_subscriber::{Layer, layer::Context, registry::LookupSpan};
+
+use super::{LayerBuildResult, ShutdownError, WithShutdown, build_env_filter};
+
+/// Configures a layer which forwards `tracing` events to a Python `logging.Logger`.
+///
+/// If `logger_name` is None, events are forwarded to the root logger. Fields of the event and
+/// of its enclosing spans are set on the Python `LogRecord`, as they would be with `extra`.
+///
+/// Events are queued and logged from a dedicated thread, so the thread which emits an event
+/// never waits to acquire the GIL.
+#[pyclass(from_py_object)]
+#[derive(Clone, Debug, Default)]
+pub(crate) struct Config {
+    pub(crate) logger_name: Option<String>,
+    pub(crate) filter: Option<String>,
+}
+
+#[pymethods]
+impl Config {
+    #[new]
+    #[pyo3(signature = (/, logger_name = None, filter = None))]
+    const fn new(logger_name: Option<String>, filter: Option<String>) -> Self {
+        Self {
+            logger_name,
+            filter,
+        }
+    }
+}
+
+impl crate::layers::Config for Config {
+    fn requires_runtime(&self) -> bool {
+        false
+    }
+
+    fn build(&self, _batch: bool) -> LayerBuildResult<WithShutdown> {
+        let env_filter = build_env_filter(self.filter.clone())?;
+        let (sender, receiver) = std::sync::mpsc::channel();
+        let (finished_sender, finished) = tokio::sync::oneshot::channel();
+        let logger_name = self.logger_name.clone();
+        std::thread::Builder::new()
+            .name("pyo3-tracing-subscriber-python-logging".to_string())
+            .spawn(move || {
+                forward_records(logger_name.as_deref(), &receiver);
+                let _ = finished_sender.send(());
+            })
+            .map_err(BuildError::from)?;
+
+        let layer = PythonLoggingLayer {
+            sender: sender.clone(),
+        }
+        .with_filter(env_filter);
+        Ok(WithShutdown {
+            layer: Box::new(layer),
+            shutdown: Box::new(move || {
+                Box::pin(async move {
+                    // If the worker has already exited, there is nothing left to flush.
+                    let _ = sender.send(Message::Shutdown);
+                    // Wait for the worker without blocking the runtime, so that the shutdown may
+                    // be cancelled (e.g. by a timeout) if the worker cannot acquire the GIL.
+                    finished.await.map_err(|_| {
+                        ShutdownError::custom("the Python logging worker thread panicked")
+                    })
+                })
+            }),
+        })
+    }
+}
+
+#[derive(thiserror::Error, Debug)]
+pub(crate) enum BuildError {
+    #[error("failed to spawn the Python logging worker thread: {0}")]
+    SpawnWorker(#[from] std::io::Error),
+}
+
+thread_local! {
+    /// Set on the worker thread, so that events emitted while Python handles a record (for
+    /// instance, by the `logging.Handler` which forwards records back to `tracing`) are not
+    /// forwarded again.
+    static IS_WORKER: Cell<bool> = const { Cell::new(false) };
+}
+
+/// Maps a [`tracing::Level`] to the corresponding Python logging level. `TRACE` has no Python
+/// equivalent and maps to 5, below `DEBUG`.
+const fn level_to_python(level: tracing::Level) -> u8 {
+    match level {
+        tracing::Level::ERROR => 40,
+        tracing::Level::WARN => 30,
+        tracing::Level::INFO => 20,
+        tracing::Level::DEBUG => 10,
+        tracing::Level::TRACE => 5,
+    }
+}
+
+enum Message {
+    Record(Record),
+    Shutdown,
+}
+
+/// The data of a `tracing` event needed to create a Python `LogRecord`.
+struct Record {
+    level: u8,
+    target: String,
+    message: String,
+    file: Option<String>,
+    line: Option<u32>,
+    fields: Fields,
+}
+
+/// The value of a recorded field, held until it can be converted to a Python object.
+#[derive(Clone, Debug)]
+enum FieldValue {
+    Bool(bool),
+    I64(i64),
+    U64(u64),
+    F64(f64),
+    Str(String),
+}
+
+impl FieldValue {
+    fn into_python(self, py: Python<'_>) -> PyResult<Bound<'_, PyAny>> {
+        match self {
+            Self::Bool(value) => value.into_bound_py_any(py),
+            Self::I64(value) => value.into_bound_py_any(py),
+            Self::U64(value) => value.into_bound_py_any(py),
+            Self::F64(value) => value.into_bound_py_any(py),
+            Self::Str(value) => value.into_bound_py_any(py),
+        }
+    }
+}
+
+/// The fields recorded on a span or event. Also stored in span extensions.
+#[derive(Clone, Debug, Default)]
+struct Fields {
+    message: Option<String>,
+    values: Vec<(&'static str, FieldValue)>,
+}
+
+impl Fields {
+    fn insert(&mut self, field: &Field, value: FieldValue) {
+        // Fields added by `tracing-log` duplicate the `LogRecord` attributes.
+        if field.name().starts_with("log.") {
+            return;
+        }
+        if field.name() == "message" {
+            if let FieldValue::Str(message) = value {
+                self.message = Some(message);
+            }
+            return;
+        }
+        match self
+            .values
+            .iter_mut()
+            .find(|(name, _)| *name == field.name())
+        {
+            Some((_, existing)) => *existing = value,
+            None => self.values.push((field.name(), value)),
+        }
+    }
+}
+
+impl tracing::field::Visit for Fields {
+    fn record_f64(&mut self, field: &Field, value: f64) {
+        self.insert(field, FieldValue::F64(value));
+    }
+
+    fn record_i64(&mut self, field: &Field, value: i64) {
+        self.insert(field, FieldValue::I64(value));
+    }
+
+    fn record_u64(&mut self, field: &Field, value: u64) {
+        self.insert(field, FieldValue::U64(value));
+    }
+
+    fn record_bool(&mut self, field: &Field, value: bool) {
+        self.insert(field, FieldValue::Bool(value));
+    }
+
+    fn record_str(&mut self, field: &Field, value: &str) {
+        self.insert(field, FieldValue::Str(value.to_string()));
+    }
+
+    fn record_debug(&mut self, field: &Field, value: &dyn std::fmt::Debug) {
+        self.insert(field, FieldValue::Str(format!("{value:?}")));
+    }
+}
+
+/// A layer which queues events to be logged by the worker thread.
+struct PythonLoggingLayer {
+    sender: Sender<Message>,
+}
+
+impl<S> Layer<S> for PythonLoggingLayer
+where
+    S: Subscriber + for<'a> LookupSpan<'a>,
+{
+    fn on_new_span(&self, attrs: &span::Attributes<'_>, id: &span::Id, ctx: Context<'_, S>) {
+        if let Some(span) = ctx.span(id) {
+            let mut fields = Fields::default();
+            attrs.record(&mut fields);
+            span.extensions_mut().insert(fields);
+        }
+    }
+
+    fn on_record(&self, id: &span::Id, values: &span::Record<'_>, ctx: Context<'_, S>) {
+        if let Some(span) = ctx.span(id)
+            && let Some(fields) = span.extensions_mut().get_mut::<Fields>()
+        {
+            values.record(fields);
+        }
+    }
+
+    fn on_event(&self, event: &Event<'_>, ctx: Context<'_, S>) {
+        if IS_WORKER.get() {
+            return;
+        }
+
+        // Fields of inner spans, and then of the event itself, take precedence.
+        let mut fields = Fields::default();
+        if let Some(scope) = ctx.event_scope(event) {
+            for span in scope.from_root() {
+                if let Some(span_fields) = span.extensions().get::<Fields>() {
+                    for (name, value) in &span_fields.values {
+                        match fields
+                            .values
+                            .iter_mut()
+                            .find(|(existing, _)| existing == name)
+                        {
+                            Some((_, existing)) => existing.clone_from(value),
+                            None => fields.values.push((name, value.clone())),
+                        }
+                    }
+                }
+            }
+        }
+        event.record(&mut fields);
+
+        let normalized_metadata = event.normalized_metadata();
+        let metadata = normalized_metadata
+            .as_ref()
+            .unwrap_or_else(|| event.metadata());
+        let record = Record {
+            level: level_to_python(*metadata.level()),
+            target: metadata.target().to_string(),
+            message: fields.message.take().unwrap_or_default(),
+            file: metadata.file().map(ToString::to_string),
+            line: metadata.line(),
+            fields,
+        };
+        // Sending only fails once the layer has been shut down.
+        let _ = self.sender.send(Message::Record(record));
+    }
+}
+
+/// Logs queued records to the Python logger until the layer is shut down.
+///
+/// The GIL is acquired once for all records queued at that time, rather than per record.
+fn forward_records(logger_name: Option<&str>, receiver: &Receiver<Message>) {
+    IS_WORKER.set(true);
+    while let Ok(Message::Record(record)) = receiver.recv() {
+        let shutdown = Python::try_attach(|py| {
+            let logger = match py
+                .import("logging")
+                .and_then(|logging| logging.call_method1("getLogger", (logger_name,)))
+            {
+                Ok(logger) => logger,
+                Err(error) => {
+                    error.write_unraisable(py, None);
+                    return false;
+                }
+            };
+            let mut record = record;
+            loop {
+                if let Err(error) = log_record(py, &logger, record) {
+                    error.write_unraisable(py, Some(&logger));
+                }
+                match receiver.try_recv() {
+                    Ok(Message::Record(next)) => record = next,
+                    Ok(Message::Shutdown) => return true,
+                    Err(_) => return false,
+                }
+            }
+        });
+        // Without an interpreter to attach to, no further records can be logged.
+        if shutdown.unwrap_or(true) {
+            break;
+        }
+    }
+}
+
+fn log_record(py: Python<'_>, logger: &Bound<'_, PyAny>, record: Record) -> PyResult<()> {
+    if !logger
+        .call_method1("isEnabledFor", (record.level,))?
+        .is_truthy()?
+    {
+        return Ok(());
+    }
+    let python_record = logger.call_method1(
+        "makeRecord",
+        (
+            logger.getattr("name")?,
+            record.level,
+            record.file.unwrap_or_default(),
+            record.line.unwrap_or_default(),
+            record.message,
+            PyTuple::empty(py),
+            py.None(),
+        ),
+    )?;
+    let fields =
+        std::iter::once(("target", FieldValue::Str(record.target))).chain(record.fields.values);
+    for (name, value) in fields {
+        // As with `extra`, fields may not overwrite the attributes of the `LogRecord`.
+        if !python_record.hasattr(name)? {
+            python_record.setattr(name, value.into_python(py)?)?;
+        }
+    }
+    logger.call_method1("handle", (python_record,))?;
+    Ok(())
+}
+
+create_init_submodule! {
+    classes: [ Config ],
+}
+
+#[cfg(test)]
+mod test {
+    use std::{sync::mpsc::RecvTimeoutError, time::Duration};
+
+    use pyo3::{ffi::c_str, prelude::*, types::PyDict};
+    use tokio::runtime::Builder;
+    use tracing_subscriber::{Registry, layer::SubscriberExt};
+
+    use crate::layers::Config as _;
+
+    #[test]
+    /// Test that events are logged to the Python logger, along with the fields of the event and
+    /// its enclosing spans.
+    fn test_forwards_events_to_python_logger() {
+        Python::initialize();
+        let locals = Python::attach(|py| {
+            let locals = PyDict::new(py);
+            py.run(
+                c_str!(
+                    r#"
+import logging
+
+class ListHandler(logging.Handler):
+    def __init__(self):
+        super().__init__()
+        self.records = []
+
+    def emit(self, record):
+        self.records.append(record)
+
+handler = ListHandler()
+logger = logging.getLogger("rust.python_logging_test")
+logger.setLevel(logging.DEBUG)
+logger.propagate = False
+logger.addHandler(handler)
+"#
+                ),
+                None,
+                Some(&locals),
+            )
+            .unwrap();
+            locals.unbind()
+        });
+
+        let layer = super::Config {
+            logger_name: Some("rust.python_logging_test".to_string()),
+            filter: Some("pyo3_tracing_subscriber=trace".to_string()),
+        }
+        .build(false)
+        .unwrap();
+        let subscriber = Registry::default().with(layer.layer);
+        tracing::subscriber::with_default(subscriber, || {
+            let _span = tracing::info_span!("request", request_id = 7).entered();
+            tracing::warn!(attempt = 2, "retrying");
+            tracing::trace!("below the logger level");
+        });
+        Builder::new_current_thread()
+            .build()
+            .unwrap()
+            .block_on((layer.shutdown)())
+            .unwrap();
+
+        Python::attach(|py| {
+            py.run(
+                c_str!(
+                    r#"
+logger.removeHandler(handler)
+assert len(handler.records) == 1, handler.records
+record = handler.records[0]
+assert record.name == "rust.python_logging_test"
+assert record.levelno == logging.WARNING
+assert record.getMessage() == "retrying"
+assert record.target == "pyo3_tracing_subscriber::layers::python_logging::test"
+assert record.request_id == 7
+assert record.attempt == 2
+"#
+                ),
+                None,
+                Some(locals.bind(py)),
+            )
+            .unwrap();
+        });
+    }
+
+    #[test]
+    /// Test that exiting the `Tracing` context manager, which Python does with the GIL held,
+    /// flushes queued records rather than deadlocking on the worker thread.
+    fn test_tracing_exit_with_gil_held() {
+        Python::initialize();
+        let (done, finished) = std::sync::mpsc::channel();
+        std::thread::spawn(move || {
+            Python::attach(|py| {
+                let package = PyModule::new(py, "python_logging_exit_test").unwrap();
+                crate::add_submodule(
+                    "python_logging_exit_test",
+                    "tracing_subscriber",
+                    py,
+                    &package,
+                )
+                .unwrap();
+                let globals = PyDict::new(py);
+                globals
+                    .set_item(
+                        "tracing_subscriber",
+                        package.getattr("tracing_subscriber").unwrap(),
+                    )
+                    .unwrap();
+                py.run(
+                    c_str!(
+                        r#"
+import logging
+import time
+
+class SlowListHandler(logging.Handler):
+    def __init__(self):
+        super().__init__()
+        self.records = []
+
+    def emit(self, record):
+        # Keep the worker busy, so that records are still queued on exit.
+        time.sleep(0.05)
+        self.records.append(record)
+
+handler = SlowListHandler()
+destination = logging.getLogger("rust.python_logging_exit_test")
+destination.setLevel(logging.DEBUG)
+destination.propagate = False
+destination.addHandler(handler)
+
+source = logging.getLogger("python_logging_exit_source")
+source.setLevel(logging.DEBUG)
+source.propagate = False
+source.addHandler(tracing_subscriber.logging.Handler())
+
+config = tracing_subscriber.CurrentThreadTracingConfig(
+    export_process=tracing_subscriber.SimpleConfig(
+        subscriber=tracing_subscriber.subscriber.Config(
+            layer=tracing_subscriber.layers.python_logging.Config(
+                logger_name="rust.python_logging_exit_test",
+                filter="python_logging_exit_source=info",
+            )
+        )
+    )
+)
+with tracing_subscriber.Tracing(config=config):
+    for i in range(5):
+        source.info("record %d", i)
+
+assert [record.getMessage() for record in handler.records] == [f"record {i}" for i in range(5)]
+"#
+                    ),
+                    Some(&globals),
+                    None,
+                )
+                .unwrap();
+            });
+            done.send(()).unwrap();
+        });
+        match finished.recv_timeout(Duration::from_secs(10)) {
+            Ok(()) => {}
+            Err(RecvTimeoutError::Timeout) => {
+                panic!("exiting the Tracing context manager deadlocked")
+            }
+            Err(RecvTimeoutError::Disconnected) => panic!("the test thread panicked"),
+        }
+    }
+}
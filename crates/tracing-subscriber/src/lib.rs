@@ -21,6 +21,8 @@
 //! * `layer-otel-otlp-file` - exports trace data with `opentelemetry-stdout`. See `crate::layers::otel_otlp_file`.
 //! * `layer-otel-otlp` - exports trace data with `opentelemetry-otlp`. See `crate::layers::otel_otlp`.
 //!
+//! Custom layers may be added by downstream libraries; see [`layers::register_config`].
+//!
 //! # Requirements and Limitations
 //!
 //! * The tracing subscribers initialized and configured _only_ capture tracing data for the pyo3
@@ -88,7 +90,8 @@ pub use contextmanager::Tracing;
 pub(crate) mod common;
 mod contextmanager;
 mod export_process;
-pub(crate) mod layers;
+pub mod layers;
+pub(crate) mod logging;
 pub(crate) mod subscriber;
 
 create_init_submodule! {
@@ -102,6 +105,7 @@ create_init_submodule! {
     errors: [TracingContextManagerError, TracingStartError, TracingShutdownError],
     submodules: [
         "layers": layers::init_submodule,
+        "logging": logging::init_submodule,
         "subscriber": subscriber::init_submodule,
         "common": common::init_submodule
     ],
@@ -148,6 +152,9 @@ create_init_submodule! {
 ///     * `tracing::fmt` - a layer which exports trace data to stdout in a non-OpenTelemetry data format.
 ///     * `opentelemetry-stdout` - a layer which exports trace data to stdout (requires the `layer-otel-otlp-file` feature).
 ///     * `opentelemetry-otlp` - a layer which exports trace data to an `OpenTelemetry` collector (requires the `layer-otel-otlp` feature).
+///     * `python_logging` - a layer which forwards events to a Python `logging.Logger`.
+/// * `logging` - a submodule which contains a Python `logging.Handler` that forwards Python log
+///   records to the configured tracing subscriber as `tracing` events.
 /// * `subscriber` - a submodule which contains utilities for initialing the tracing subscriber
 ///   with the configured layer. Currently, the tracing subscriber is initialized as
 ///   `tracing::subscriber::Registry::default().with(layer)`.
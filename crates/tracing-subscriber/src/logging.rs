@@ -0,0 +1,181 @@
+// Copyright 2023 Rigetti Computing
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+// http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! This module bridges Python `logging` records into the configured tracing subscriber.
+//!
+//! The Python `Handler` is a subclass of `logging.Handler` whose `emit` method is implemented
+//! in Rust. Each record is forwarded as a `tracing` event using [`tracing_log`], so that the
+//! logger name becomes the event target and the currently entered span becomes its parent.
+use pyo3::{
+    prelude::*,
+    sync::PyOnceLock,
+    types::{PyDict, PyType},
+};
+use tracing_log::log;
+
+const HANDLER_DOC: &str = "A `logging.Handler` which forwards Python log records to the tracing \
+subscriber as `tracing` events. The logger name is used as the event target.";
+
+/// A `logging.Formatter` with the default format, which renders the record message along with
+/// any exception and stack information. Timestamps and levels are left to the tracing layers.
+static FORMATTER: PyOnceLock<Py<PyAny>> = PyOnceLock::new();
+
+/// Maps a Python logging level to the nearest [`log::Level`]. Levels above `ERROR`, such as
+/// `CRITICAL`, map to [`log::Level::Error`].
+const fn level_from_python(levelno: i64) -> log::Level {
+    match levelno {
+        40.. => log::Level::Error,
+        30..40 => log::Level::Warn,
+        20..30 => log::Level::Info,
+        10..20 => log::Level::Debug,
+        _ => log::Level::Trace,
+    }
+}
+
+/// The `emit` method of the Python `Handler`. Errors are reported as unraisable, rather than
+/// raised, so that a failure to forward a record does not interrupt the caller.
+#[pyfunction]
+fn emit(py: Python<'_>, record: &Bound<'_, PyAny>) {
+    if let Err(error) = emit_record(py, record) {
+        error.write_unraisable(py, Some(record));
+    }
+}
+
+fn emit_record(py: Python<'_>, record: &Bound<'_, PyAny>) -> PyResult<()> {
+    let formatter = FORMATTER.get_or_try_init(py, || {
+        py.import("logging")?
+            .getattr("Formatter")?
+            .call0()
+            .map(Bound::unbind)
+    })?;
+    let message: String = formatter
+        .bind(py)
+        .call_method1("format", (record,))?
+        .extract()?;
+    let target: String = record.getattr("name")?.extract()?;
+    let level = level_from_python(record.getattr("levelno")?.extract()?);
+    let module: Option<String> = record.getattr("module")?.extract()?;
+    let file: Option<String> = record.getattr("pathname")?.extract()?;
+    let line: Option<u32> = record.getattr("lineno")?.extract()?;
+
+    // The GIL is not needed to dispatch the event, and layers may block on I/O.
+    py.detach(|| {
+        tracing_log::format_trace(
+            &log::Record::builder()
+                .args(format_args!("{message}"))
+                .level(level)
+                .target(&target)
+                .module_path(module.as_deref())
+                .file(file.as_deref())
+                .line(line)
+                .build(),
+        )
+    })?;
+    Ok(())
+}
+
+/// Adds the `logging` submodule, which contains the Python `Handler` class.
+pub(crate) fn init_submodule<'py>(
+    name: &str,
+    py: Python<'py>,
+    m: &Bound<'py, PyModule>,
+) -> PyResult<()> {
+    let base = py.import("logging")?.getattr("Handler")?;
+    let namespace = PyDict::new(py);
+    namespace.set_item("__module__", name)?;
+    namespace.set_item("__doc__", HANDLER_DOC)?;
+    namespace.set_item("emit", wrap_pyfunction!(emit, m)?)?;
+    let handler = py
+        .get_type::<PyType>()
+        .call1(("Handler", (base,), namespace))?;
+    m.add("Handler", handler)?;
+    Ok(())
+}
+
+#[cfg(test)]
+mod test {
+    use std::{
+        io::Write,
+        sync::{Arc, Mutex},
+    };
+
+    use pyo3::{ffi::c_str, prelude::*, types::PyDict};
+
+    /// A writer which collects the output of the `fmt` layer.
+    #[derive(Clone, Default)]
+    struct Output(Arc<Mutex<Vec<u8>>>);
+
+    impl Write for Output {
+        fn write(&mut self, buf: &[u8]) -> std::io::Result<usize> {
+            self.0.lock().unwrap().write(buf)
+        }
+
+        fn flush(&mut self) -> std::io::Result<()> {
+            Ok(())
+        }
+    }
+
+    #[test]
+    /// Test that Python log records are forwarded as events within the current span.
+    fn test_handler_forwards_records() {
+        Python::initialize();
+        let output = Output::default();
+        let subscriber = tracing_subscriber::fmt()
+            .with_ansi(false)
+            .with_max_level(tracing::Level::TRACE)
+            .with_writer({
+                let output = output.clone();
+                move || output.clone()
+            })
+            .finish();
+
+        tracing::subscriber::with_default(subscriber, || {
+            let _span = tracing::info_span!("parent").entered();
+            Python::attach(|py| {
+                let module = PyModule::new(py, "logging_test").unwrap();
+                super::init_submodule("logging_test", py, &module).unwrap();
+                let locals = PyDict::new(py);
+                locals
+                    .set_item("Handler", module.getattr("Handler").unwrap())
+                    .unwrap();
+                py.run(
+                    c_str!(
+                        r#"
+import logging
+
+handler = Handler()
+assert isinstance(handler, logging.Handler)
+logger = logging.getLogger("example.logger")
+logger.setLevel(logging.DEBUG)
+logger.propagate = False
+logger.addHandler(handler)
+logger.warning("hello %s", "there")
+logger.debug("debugging")
+logger.removeHandler(handler)
+"#
+                    ),
+                    None,
+                    Some(&locals),
+                )
+                .unwrap();
+            });
+        });
+
+        let output = String::from_utf8(output.0.lock().unwrap().clone()).unwrap();
+        let lines = output.lines().collect::<Vec<_>>();
+        assert_eq!(lines.len(), 2, "{output}");
+        assert!(lines[0].contains("WARN parent: example.logger: hello there"));
+        assert!(lines[1].contains("DEBUG parent: example.logger: debugging"));
+    }
+}
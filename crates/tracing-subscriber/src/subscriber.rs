@@ -12,14 +12,18 @@
 // See the License for the specific language governing permissions and
 // limitations under the License.
 use crate::create_init_submodule;
-use pyo3::prelude::*;
+use pyo3::{exceptions::PyValueError, prelude::*};
 use tracing::subscriber::DefaultGuard;
 use tracing_subscriber::{Registry, layer::Layered, prelude::__tracing_subscriber_SubscriberExt};
 
 #[derive(thiserror::Error, Debug)]
 pub(crate) enum ShutdownError {
-    #[error("failed to shutdown configured layer: {0}")]
-    LayerShutdown(#[from] crate::layers::ShutdownError),
+    #[error(
+        "failed to shutdown {} configured layer(s): {}",
+        .0.len(),
+        .0.iter().map(ToString::to_string).collect::<Vec<_>>().join("; ")
+    )]
+    LayerShutdown(Vec<crate::layers::ShutdownError>),
 }
 
 type ShutdownResult<T> = Result<T, ShutdownError>;
@@ -109,7 +113,7 @@ impl Default for PyConfig {
         let layer = super::layers::PyConfig::default();
         Self {
             subscriber_config: Box::new(TracingSubscriberRegistryConfig {
-                layer_config: Box::new(layer),
+                layer_configs: vec![Box::new(layer)],
             }),
         }
     }
@@ -124,40 +128,80 @@ impl core::fmt::Debug for PyConfig {
 #[pymethods]
 impl PyConfig {
     #[new]
-    #[pyo3(signature = (/, layer = None))]
+    #[pyo3(signature = (/, layer = None, layers = None))]
     #[allow(clippy::pedantic)]
-    fn new(layer: Option<super::layers::PyConfig>) -> PyResult<Self> {
-        let layer = layer.unwrap_or_default();
+    fn new(
+        layer: Option<super::layers::PyConfig>,
+        layers: Option<Vec<super::layers::PyConfig>>,
+    ) -> PyResult<Self> {
+        let layers = match (layer, layers) {
+            (Some(_), Some(_)) => {
+                return Err(PyValueError::new_err(
+                    "only one of `layer` or `layers` may be specified",
+                ));
+            }
+            (Some(layer), None) => vec![layer],
+            (None, Some(layers)) if layers.is_empty() => {
+                return Err(PyValueError::new_err("`layers` must not be empty"));
+            }
+            (None, Some(layers)) => layers,
+            (None, None) => vec![super::layers::PyConfig::default()],
+        };
         Ok(Self {
             subscriber_config: Box::new(TracingSubscriberRegistryConfig {
-                layer_config: Box::new(layer),
+                layer_configs: layers
+                    .into_iter()
+                    .map(|layer| -> Box<dyn super::layers::Config> { Box::new(layer) })
+                    .collect(),
             }),
         })
     }
 }
 
 /// A concrete implementation of [`Config`] that wraps a [`tracing_subscriber::Registry`]. This is
-/// used internally to build a [`tracing_subscriber::Registry`] from a [`crate::layers::PyConfig`].
+/// used internally to build a [`tracing_subscriber::Registry`] from one or more
+/// [`crate::layers::PyConfig`]s.
 #[derive(Clone)]
 pub(super) struct TracingSubscriberRegistryConfig {
-    pub(super) layer_config: Box<dyn super::layers::Config>,
+    pub(super) layer_configs: Vec<Box<dyn super::layers::Config>>,
 }
 
 impl Config for TracingSubscriberRegistryConfig {
     fn requires_runtime(&self) -> bool {
-        self.layer_config.requires_runtime()
+        self.layer_configs
+            .iter()
+            .any(|layer_config| layer_config.requires_runtime())
     }
 
     fn build(&self, batch: bool) -> SubscriberBuildResult<WithShutdown> {
-        let layer = self.layer_config.clone().build(batch)?;
-        let subscriber = Registry::default().with(layer.layer);
-        let shutdown = layer.shutdown;
+        let (layers, shutdowns): (Vec<_>, Vec<_>) = self
+            .layer_configs
+            .iter()
+            .map(|layer_config| {
+                layer_config
+                    .build(batch)
+                    .map(|layer| (layer.layer, layer.shutdown))
+            })
+            .collect::<Result<Vec<_>, _>>()?
+            .into_iter()
+            .unzip();
+        let subscriber = Registry::default().with(layers);
         Ok(WithShutdown {
             subscriber: Box::new(subscriber),
             shutdown: Box::new(move || {
                 Box::pin(async move {
-                    shutdown().await?;
-                    Ok(())
+                    // Shut down every layer, in the order configured, even if an earlier one fails.
+                    let mut errors = Vec::new();
+                    for shutdown in shutdowns {
+                        if let Err(error) = shutdown().await {
+                            errors.push(error);
+                        }
+                    }
+                    if errors.is_empty() {
+                        Ok(())
+                    } else {
+                        Err(ShutdownError::LayerShutdown(errors))
+                    }
                 })
             }),
         })
@@ -214,3 +258,16 @@ create_init_submodule! {
         PyConfig
     ],
 }
+
+#[cfg(test)]
+mod test {
+    use super::PyConfig;
+
+    #[test]
+    /// Test that a subscriber cannot be configured with an empty list of layers, which would
+    /// silently discard all trace data.
+    fn test_empty_layers_rejected() {
+        assert!(PyConfig::new(None, Some(Vec::new())).is_err());
+        assert!(PyConfig::new(None, None).is_ok());
+    }
+}